@@ -0,0 +1,199 @@
+// Aho–Corasick automaton, which finds several literal patterns in one pass
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::fold::fold;
+
+/*
+  Node of the trie: transitions by the next character,
+  failure link to the longest proper suffix, which is also in the trie,
+  and indexes of all patterns, which end here (including suffixes)
+*/
+#[derive(Debug, Clone, Default)]
+struct Node {
+    next: HashMap<char, usize>,
+    fail: usize,
+    depth: usize,
+    outputs: Vec<usize>,
+}
+
+/*
+  Automaton built from a list of patterns,
+  with case insensitive search patterns and text are folded
+*/
+#[derive(Debug, Clone)]
+pub struct AhoCorasick {
+    nodes: Vec<Node>,
+    lengths: Vec<usize>,
+    case_sensitive: bool,
+}
+
+impl AhoCorasick {
+    pub fn new(patterns: &[String], case_sensitive: bool) -> AhoCorasick {
+        let mut nodes = vec![Node::default()];
+        let mut lengths = Vec::with_capacity(patterns.len());
+
+        // Put every pattern into the trie
+        for (index, pattern) in patterns.iter().enumerate() {
+            let mut state = 0;
+
+            for c in pattern.chars() {
+                let c = if case_sensitive { c } else { fold(c) };
+
+                state = match nodes[state].next.get(&c) {
+                    Some(next) => *next,
+                    None => {
+                        let child = nodes.len();
+                        let depth = nodes[state].depth + 1;
+                        nodes.push(Node {
+                            depth,
+                            ..Node::default()
+                        });
+                        nodes[state].next.insert(c, child);
+                        child
+                    }
+                };
+            }
+
+            nodes[state].outputs.push(index);
+            lengths.push(pattern.chars().count());
+        }
+
+        // Failure links are found level by level, so shorter suffixes are ready first
+        let mut queue: VecDeque<usize> = nodes[0].next.values().copied().collect();
+
+        while let Some(state) = queue.pop_front() {
+            let next: Vec<(char, usize)> =
+                nodes[state].next.iter().map(|(c, n)| (*c, *n)).collect();
+
+            for (c, child) in next {
+                let mut fail = nodes[state].fail;
+                while fail != 0 && !nodes[fail].next.contains_key(&c) {
+                    fail = nodes[fail].fail;
+                }
+                let fail = nodes[fail].next.get(&c).copied().unwrap_or(0);
+
+                nodes[child].fail = fail;
+                let inherited = nodes[fail].outputs.clone();
+                nodes[child].outputs.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        AhoCorasick {
+            nodes,
+            lengths,
+            case_sensitive,
+        }
+    }
+
+    // Move from the state by the character, following failure links when needed
+    fn step(&self, mut state: usize, c: char) -> usize {
+        loop {
+            if let Some(next) = self.nodes[state].next.get(&c) {
+                return *next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.nodes[state].fail;
+        }
+    }
+
+    /*
+      Find the leftmost match, which starts at `start` or later, the longest
+      one wins among matches with the same start, and then the first pattern,
+      returns byte range and index of the matched pattern
+    */
+    pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize, usize)> {
+        let mut best: Option<(usize, usize, usize)> = None;
+        // Byte offsets of scanned characters, to turn pattern lengths into ranges
+        let mut offsets = Vec::new();
+        let mut state = 0;
+
+        // Empty patterns are matched right away
+        if let Some(pattern) = self.nodes[0].outputs.first() {
+            best = Some((start, start, *pattern));
+        }
+
+        for (index, c) in text[start..].char_indices() {
+            let position = start + index;
+
+            // All later matches start inside of the current state, which is after the best match
+            if let Some((best_start, _, _)) = best {
+                let depth = self.nodes[state].depth;
+                let earliest = offsets
+                    .get(offsets.len() - depth)
+                    .copied()
+                    .unwrap_or(position);
+
+                if earliest > best_start {
+                    break;
+                }
+            }
+
+            offsets.push(position);
+            state = self.step(state, if self.case_sensitive { c } else { fold(c) });
+            let end = position + c.len_utf8();
+
+            for pattern in &self.nodes[state].outputs {
+                // Empty pattern ends and starts at the same place
+                let found_start = offsets
+                    .get(offsets.len() - self.lengths[*pattern])
+                    .copied()
+                    .unwrap_or(end);
+
+                let better = match best {
+                    None => true,
+                    Some((best_start, best_end, best_pattern)) => {
+                        (found_start, std::cmp::Reverse(end), *pattern)
+                            < (best_start, std::cmp::Reverse(best_end), best_pattern)
+                    }
+                };
+
+                if better {
+                    best = Some((found_start, end, *pattern));
+                }
+            }
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn automaton(patterns: &[&str], case_sensitive: bool) -> AhoCorasick {
+        let patterns: Vec<String> = patterns.iter().map(|pattern| pattern.to_string()).collect();
+        AhoCorasick::new(&patterns, case_sensitive)
+    }
+
+    #[test]
+    fn leftmost_longest_match() {
+        let automaton = automaton(&["he", "she", "hers", "his"], true);
+
+        assert_eq!(Some((1, 4, 1)), automaton.find_at("ushers", 0));
+        assert_eq!(Some((2, 6, 2)), automaton.find_at("ushers", 2));
+        assert_eq!(Some((0, 3, 3)), automaton.find_at("his", 0));
+        assert_eq!(None, automaton.find_at("hat", 0));
+    }
+
+    #[test]
+    fn failure_links_and_folding() {
+        let automaton = automaton(&["abcd", "bc", "ΣΟΦ"], false);
+
+        assert_eq!(Some((1, 3, 1)), automaton.find_at("abce", 0));
+        assert_eq!(Some((0, 4, 0)), automaton.find_at("ABCD", 0));
+        assert_eq!(Some((2, 8, 2)), automaton.find_at("a σοφός", 0));
+    }
+
+    #[test]
+    fn empty_pattern_matches_everywhere() {
+        let automaton = automaton(&["", "ab"], true);
+
+        assert_eq!(Some((0, 2, 1)), automaton.find_at("abc", 0));
+        assert_eq!(Some((1, 1, 0)), automaton.find_at("xyz", 1));
+    }
+}
@@ -0,0 +1,136 @@
+// Splitting of command line arguments into flags and values
+
+/*
+  Single command line argument: short flag (`-i`),
+  long flag (`--count` or `--context=2` with inline value) or plain value
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Short(char),
+    Long(String, Option<String>),
+    Value(String),
+}
+
+/*
+  Iterator over parsed arguments, which splits
+  combined short flags (`-in` is `-i -n`) and treats
+  everything after `--` as values
+*/
+pub struct ArgParser<I> {
+    args: I,
+    pending: Vec<char>,
+    only_values: bool,
+}
+
+impl<I: Iterator<Item = String>> ArgParser<I> {
+    pub fn new(args: I) -> ArgParser<I> {
+        ArgParser {
+            args,
+            pending: Vec::new(),
+            only_values: false,
+        }
+    }
+
+    /*
+      Take value for the flag, which needs it:
+      rest of combined short flags (`-A3`) or next argument (`-A 3`)
+    */
+    pub fn value(&mut self) -> Option<String> {
+        if !self.pending.is_empty() {
+            return Some(self.pending.drain(..).rev().collect());
+        }
+
+        self.args.next()
+    }
+}
+
+impl<I: Iterator<Item = String>> Iterator for ArgParser<I> {
+    type Item = Arg;
+
+    fn next(&mut self) -> Option<Arg> {
+        // Rest of combined short flags, kept in reversed order
+        if let Some(c) = self.pending.pop() {
+            return Some(Arg::Short(c));
+        }
+
+        let arg = self.args.next()?;
+
+        if self.only_values {
+            return Some(Arg::Value(arg));
+        }
+
+        if arg == "--" {
+            self.only_values = true;
+            return self.next();
+        }
+
+        if let Some(name) = arg.strip_prefix("--") {
+            return match name.split_once('=') {
+                Some((name, value)) => Some(Arg::Long(name.to_string(), Some(value.to_string()))),
+                None => Some(Arg::Long(name.to_string(), None)),
+            };
+        }
+
+        // Single `-` is a value, e.g. name of standard input
+        if arg.len() > 1 && arg.starts_with('-') {
+            self.pending = arg[1..].chars().rev().collect();
+            return self.next();
+        }
+
+        Some(Arg::Value(arg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Vec<Arg> {
+        ArgParser::new(args.iter().map(|arg| arg.to_string())).collect()
+    }
+
+    #[test]
+    fn combined_short_flags() {
+        assert_eq!(
+            vec![
+                Arg::Short('i'),
+                Arg::Short('n'),
+                Arg::Short('c'),
+                Arg::Value(String::from("query")),
+            ],
+            parse(&["-inc", "query"])
+        );
+    }
+
+    #[test]
+    fn values_after_double_dash() {
+        assert_eq!(
+            vec![
+                Arg::Long(String::from("count"), None),
+                Arg::Value(String::from("-v")),
+                Arg::Value(String::from("--")),
+                Arg::Value(String::from("-")),
+            ],
+            parse(&["--count", "--", "-v", "--", "-"])
+        );
+    }
+
+    #[test]
+    fn flag_values() {
+        let args = ["-nA3", "-B", "2", "--context=1"]
+            .iter()
+            .map(|arg| arg.to_string());
+        let mut parser = ArgParser::new(args);
+
+        assert_eq!(Some(Arg::Short('n')), parser.next());
+        assert_eq!(Some(Arg::Short('A')), parser.next());
+        assert_eq!(Some(String::from("3")), parser.value());
+        assert_eq!(Some(Arg::Short('B')), parser.next());
+        assert_eq!(Some(String::from("2")), parser.value());
+        assert_eq!(
+            Some(Arg::Long(String::from("context"), Some(String::from("1")))),
+            parser.next()
+        );
+        assert_eq!(None, parser.next());
+    }
+}
@@ -0,0 +1,186 @@
+// Lines around matches, printed with `-A`, `-B` and `-C` flags
+
+use std::collections::VecDeque;
+
+use crate::{lines_with_offsets, Match};
+
+/*
+  Line of the output: matched line, line around it,
+  or `--` separator between groups, which aren't adjacent
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line<'a> {
+    Match(Match<'a>),
+    Context { line_number: usize, line: &'a str },
+    Separator,
+}
+
+/*
+  Tracker of lines around matches, which works line by line,
+  so it could be used while streaming: it keeps only `before` last lines
+*/
+#[derive(Debug)]
+pub struct ContextTracker<T> {
+    before: usize,
+    after: usize,
+    buffer: VecDeque<(usize, T)>,
+    printed: usize,
+    after_until: usize,
+}
+
+impl<T> ContextTracker<T> {
+    pub fn new(before: usize, after: usize) -> ContextTracker<T> {
+        ContextTracker {
+            before,
+            after,
+            buffer: VecDeque::with_capacity(before),
+            printed: 0,
+            after_until: 0,
+        }
+    }
+
+    /*
+      Check if not matched line is needed as context,
+      so caller could skip copying of lines, which are thrown away
+    */
+    pub fn keeps(&self, line_number: usize) -> bool {
+        self.before > 0 || line_number <= self.after_until
+    }
+
+    /*
+      Feed not matched line, returns it back
+      when it must be printed right now (after previous match)
+    */
+    pub fn context_line(&mut self, line_number: usize, line: T) -> Option<(usize, T)> {
+        if line_number <= self.after_until {
+            self.printed = line_number;
+            return Some((line_number, line));
+        }
+
+        if self.before > 0 {
+            if self.buffer.len() == self.before {
+                self.buffer.pop_front();
+            }
+            self.buffer.push_back((line_number, line));
+        }
+
+        None
+    }
+
+    /*
+      Feed matched line, returns whether separator is needed
+      and lines, which must be printed before the match
+    */
+    pub fn matched_line(&mut self, line_number: usize) -> (bool, Vec<(usize, T)>) {
+        let before: Vec<(usize, T)> = self.buffer.drain(..).collect();
+        let first = before.first().map_or(line_number, |(number, _)| *number);
+
+        let separator =
+            (self.before > 0 || self.after > 0) && self.printed > 0 && first > self.printed + 1;
+
+        self.printed = line_number;
+        self.after_until = line_number + self.after;
+
+        (separator, before)
+    }
+}
+
+/*
+  Add `before` and `after` lines around every match,
+  overlapping groups are merged, so no line is printed twice
+*/
+pub fn with_context<'a>(
+    contents: &'a str,
+    matches: Vec<Match<'a>>,
+    before: usize,
+    after: usize,
+) -> Vec<Line<'a>> {
+    let mut tracker = ContextTracker::new(before, after);
+    let mut matches = matches.into_iter().peekable();
+    let mut output = Vec::new();
+
+    for (index, (_, line)) in lines_with_offsets(contents).enumerate() {
+        let line_number = index + 1;
+
+        match matches.next_if(|found| found.line_number == line_number) {
+            Some(found) => {
+                let (separator, before) = tracker.matched_line(line_number);
+
+                if separator {
+                    output.push(Line::Separator);
+                }
+                for (line_number, line) in before {
+                    output.push(Line::Context { line_number, line });
+                }
+                output.push(Line::Match(found));
+            }
+            None => {
+                if let Some((line_number, line)) = tracker.context_line(line_number, line) {
+                    output.push(Line::Context { line_number, line });
+                }
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(line_number: usize, line: &str) -> Match<'_> {
+        Match {
+            line_number,
+            column: 1,
+            byte_offset: 0,
+            line,
+            submatches: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    fn render(lines: Vec<Line>) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| match line {
+                Line::Match(found) => format!("{}:{}", found.line_number, found.line),
+                Line::Context { line_number, line } => format!("{}-{}", line_number, line),
+                Line::Separator => String::from("--"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_are_separated() {
+        let contents = "a\nb\nc\nd\ne\nf\ng\nh";
+        let matches = vec![found(2, "b"), found(7, "g")];
+
+        assert_eq!(
+            vec!["1-a", "2:b", "3-c", "--", "6-f", "7:g", "8-h"],
+            render(with_context(contents, matches, 1, 1))
+        );
+    }
+
+    #[test]
+    fn overlapping_groups_are_merged() {
+        let contents = "a\nb\nc\nd\ne";
+        let matches = vec![found(2, "b"), found(4, "d")];
+
+        assert_eq!(
+            vec!["1-a", "2:b", "3-c", "4:d", "5-e"],
+            render(with_context(contents, matches, 2, 2))
+        );
+    }
+
+    #[test]
+    fn only_after_context() {
+        let contents = "a\nb\nc\nd\ne";
+        let matches = vec![found(1, "a"), found(2, "b"), found(5, "e")];
+
+        assert_eq!(
+            vec!["1:a", "2:b", "3-c", "--", "5:e"],
+            render(with_context(contents, matches, 0, 1))
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Unicode simple case folding, used for case insensitive search
+
+/*
+  Characters, which simple case folding maps differently
+  from their lowercase form (or which have no lowercase form at all)
+*/
+const SPECIAL_FOLDS: [(char, char); 23] = [
+    ('\u{00B5}', '\u{03BC}'), // micro sign -> mu
+    ('\u{017F}', 's'),        // long s
+    ('\u{0345}', '\u{03B9}'), // combining ypogegrammeni -> iota
+    ('\u{03C2}', '\u{03C3}'), // final sigma -> sigma
+    ('\u{03D0}', '\u{03B2}'), // beta symbol
+    ('\u{03D1}', '\u{03B8}'), // theta symbol
+    ('\u{03D5}', '\u{03C6}'), // phi symbol
+    ('\u{03D6}', '\u{03C0}'), // pi symbol
+    ('\u{03F0}', '\u{03BA}'), // kappa symbol
+    ('\u{03F1}', '\u{03C1}'), // rho symbol
+    ('\u{03F5}', '\u{03B5}'), // lunate epsilon symbol
+    ('\u{1C80}', '\u{0432}'), // rounded ve
+    ('\u{1C81}', '\u{0434}'), // long-legged de
+    ('\u{1C82}', '\u{043E}'), // narrow o
+    ('\u{1C83}', '\u{0441}'), // wide es
+    ('\u{1C84}', '\u{0442}'), // tall te
+    ('\u{1C85}', '\u{0442}'), // three-legged te
+    ('\u{1C86}', '\u{044A}'), // tall hard sign
+    ('\u{1C87}', '\u{0463}'), // tall yat
+    ('\u{1C88}', '\u{A64B}'), // unblended uk
+    ('\u{1E9B}', '\u{1E61}'), // long s with dot above
+    ('\u{1E9E}', '\u{00DF}'), // capital sharp s
+    ('\u{1FBE}', '\u{03B9}'), // prosgegrammeni -> iota
+];
+
+/*
+  Fold single character, so all its case forms become the same character.
+  Simple folding never changes amount of characters, so `ß` stays `ß`
+  and `İ` stays `İ` (their lowercase forms are longer than one character)
+*/
+pub fn fold(c: char) -> char {
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+
+    if let Some((_, folded)) = SPECIAL_FOLDS.iter().find(|(from, _)| *from == c) {
+        return *folded;
+    }
+
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(lower), None) => lower,
+        _ => c,
+    }
+}
+
+// Fold every character of the text
+pub fn fold_str(text: &str) -> String {
+    text.chars().map(fold).collect()
+}
+
+/*
+  All characters, which fold to the same character as `c`,
+  used to match character classes case insensitively
+*/
+pub fn case_variants(c: char) -> Vec<char> {
+    let folded = fold(c);
+    let mut variants = vec![c, folded];
+
+    let mut upper = folded.to_uppercase();
+    if let (Some(upper), None) = (upper.next(), upper.next()) {
+        variants.push(upper);
+    }
+
+    for (from, to) in SPECIAL_FOLDS {
+        if to == folded {
+            variants.push(from);
+        }
+    }
+
+    variants.sort_unstable();
+    variants.dedup();
+    variants
+}
+
+/*
+  Length in bytes of the text prefix, which is equal to already folded query,
+  works without allocation, because folding keeps amount of characters
+*/
+pub fn prefix_len(text: &str, folded_query: &str) -> Option<usize> {
+    let mut query = folded_query.chars();
+    let mut text_chars = text.char_indices();
+
+    loop {
+        let expected = match query.next() {
+            Some(expected) => expected,
+            None => return Some(text_chars.next().map_or(text.len(), |(index, _)| index)),
+        };
+
+        match text_chars.next() {
+            Some((_, c)) if fold(c) == expected => {}
+            _ => return None,
+        }
+    }
+}
+
+/*
+  Find the first byte range in the text starting at `start` or later,
+  which is equal to already folded query
+*/
+pub fn find_at(text: &str, folded_query: &str, start: usize) -> Option<(usize, usize)> {
+    text[start..]
+        .char_indices()
+        .map(|(index, _)| start + index)
+        .chain(std::iter::once(text.len()))
+        .find_map(|index| prefix_len(&text[index..], folded_query).map(|len| (index, index + len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_folding() {
+        assert_eq!("hello", fold_str("HeLLo"));
+        assert_eq!("σσσ", fold_str("Σσς"));
+        assert_eq!("ßß", fold_str("ẞß"));
+        assert_eq!("straße", fold_str("STRAßE"));
+
+        // Dotted capital I has no simple folding, so it isn't broken into two characters
+        assert_eq!("i̇".chars().count(), "İ".to_lowercase().chars().count());
+        assert_eq!("İ", fold_str("İ"));
+    }
+
+    #[test]
+    fn variants_of_character() {
+        assert_eq!(vec!['Σ', 'ς', 'σ'], case_variants('σ'));
+        assert_eq!(vec!['K', 'k', '\u{212A}'], case_variants('\u{212A}'));
+    }
+
+    #[test]
+    fn find_keeps_original_offsets() {
+        let text = "İstanbul ΌΣΟΣ straße";
+
+        assert_eq!(Some((10, 18)), find_at(text, &fold_str("όσος"), 0));
+        assert_eq!(Some((19, 26)), find_at(text, &fold_str("STRAẞE"), 0));
+        assert_eq!(None, find_at(text, &fold_str("istanbul"), 0));
+        assert_eq!(Some((2, 9)), find_at(text, "stanbul", 0));
+    }
+}
@@ -0,0 +1,319 @@
+// Fuzzy matching: query characters must appear in the line in order, but not together
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::path::PathBuf;
+
+use crate::fold::fold;
+
+// Score of every matched character
+const MATCH: i64 = 16;
+// Matched character starts a word (line start, or after non alphanumeric one)
+const BONUS_WORD_START: i64 = 8;
+// Matched character starts a word in camelCase or a number
+const BONUS_CAMEL_CASE: i64 = 7;
+// Matched character follows the previous matched one
+const BONUS_CONSECUTIVE: i64 = 4;
+// Penalty for the first skipped character between matched ones, and for every next one
+const GAP_START: i64 = 3;
+const GAP_EXTENSION: i64 = 1;
+// Largest table of the best alignment (query characters by line characters), about 1 MiB
+const MAX_TABLE_CELLS: usize = 1 << 16;
+
+// Score of matched characters at the indices, with bonuses and gap penalties between them
+fn alignment_score(indices: &[usize], bonuses: &[i64]) -> i64 {
+    let mut score = 0;
+
+    for (n, &j) in indices.iter().enumerate() {
+        score += MATCH + bonuses[j];
+        if let Some(&k) = n.checked_sub(1).and_then(|n| indices.get(n)) {
+            score += if k + 1 == j {
+                BONUS_CONSECUTIVE
+            } else {
+                -GAP_START - GAP_EXTENSION * (j - k - 2) as i64
+            };
+        }
+    }
+
+    score
+}
+
+/*
+  Found fuzzy match: score (bigger is better)
+  and byte offsets of matched characters in the line
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+/*
+  Case is ignored, unless query has upper case characters (smart case),
+  so `cfg` finds `Config`, but `Cfg` doesn't find `config`
+*/
+pub fn smart_case_sensitive(query: &str) -> bool {
+    query.chars().any(char::is_uppercase)
+}
+
+// Bonus for matching the character, which depends on the one before it
+fn bonus(previous: Option<char>, c: char) -> i64 {
+    match previous {
+        None => BONUS_WORD_START,
+        Some(previous) if !previous.is_alphanumeric() && c.is_alphanumeric() => BONUS_WORD_START,
+        Some(previous) if previous.is_lowercase() && c.is_uppercase() => BONUS_CAMEL_CASE,
+        Some(previous) if !previous.is_numeric() && c.is_numeric() => BONUS_CAMEL_CASE,
+        _ => 0,
+    }
+}
+
+/*
+  Find the best alignment of query characters in the line (the first one in too long lines),
+  returns `None` when query isn't a subsequence of the line
+*/
+pub fn fuzzy_match(query: &str, line: &str, case_sensitive: bool) -> Option<FuzzyMatch> {
+    let normalize = |c: char| if case_sensitive { c } else { fold(c) };
+    let query: Vec<char> = query.chars().map(normalize).collect();
+    let text: Vec<(usize, char)> = line.char_indices().collect();
+
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    // Cheap check first, most lines don't match at all
+    let mut greedy = Vec::with_capacity(query.len());
+    for (j, (_, c)) in text.iter().enumerate() {
+        if greedy.len() < query.len() && normalize(*c) == query[greedy.len()] {
+            greedy.push(j);
+        }
+    }
+    if greedy.len() < query.len() {
+        return None;
+    }
+
+    let bonuses: Vec<i64> = (0..text.len())
+        .map(|j| bonus(j.checked_sub(1).map(|k| text[k].1), text[j].1))
+        .collect();
+
+    // Table of long line (e.g. minified file) would be too large, first characters found are taken
+    if query.len().saturating_mul(text.len()) > MAX_TABLE_CELLS {
+        return Some(FuzzyMatch {
+            score: alignment_score(&greedy, &bonuses),
+            positions: greedy.iter().map(|&j| text[j].0).collect(),
+        });
+    }
+
+    /*
+      `scores[i][j]` is the best score of the first `i + 1` query characters,
+      when the last of them is matched at `j`-th character of the line
+    */
+    let mut scores: Vec<Vec<Option<i64>>> = vec![vec![None; text.len()]; query.len()];
+
+    for (i, expected) in query.iter().enumerate() {
+        // Best score to continue after a gap, penalties are already taken
+        let mut gapped: Option<i64> = None;
+
+        for j in 0..text.len() {
+            if j >= 2 && i > 0 {
+                let extended = gapped.map(|score| score - GAP_EXTENSION);
+                let started = scores[i - 1][j - 2].map(|score| score - GAP_START);
+                gapped = extended.max(started);
+            }
+
+            if normalize(text[j].1) != *expected {
+                continue;
+            }
+
+            let current = MATCH + bonuses[j];
+            scores[i][j] = if i == 0 {
+                Some(current)
+            } else {
+                let consecutive = j
+                    .checked_sub(1)
+                    .and_then(|k| scores[i - 1][k])
+                    .map(|score| score + BONUS_CONSECUTIVE);
+
+                consecutive.max(gapped).map(|score| score + current)
+            };
+        }
+    }
+
+    // Best end of the match, the earliest one wins among equal scores
+    let (mut j, score) = scores[query.len() - 1]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by(|(a_j, a), (b_j, b)| a.cmp(b).then(b_j.cmp(a_j)))?;
+
+    // Walk back, finding the character before, which gave the score
+    let mut positions = vec![text[j].0];
+    let mut expected_score = score;
+
+    for i in (1..query.len()).rev() {
+        let before = expected_score - MATCH - bonuses[j];
+
+        let k = (0..j).rev().find(|k| {
+            scores[i - 1][*k].is_some_and(|score| {
+                let transition = if *k + 1 == j {
+                    BONUS_CONSECUTIVE
+                } else {
+                    -GAP_START - GAP_EXTENSION * (j - k - 2) as i64
+                };
+                score + transition == before
+            })
+        })?;
+
+        expected_score = scores[i - 1][k]?;
+        positions.push(text[k].0);
+        j = k;
+    }
+
+    positions.reverse();
+    Some(FuzzyMatch { score, positions })
+}
+
+/*
+  Line found in fuzzy mode, better lines are greater,
+  among equal scores earlier files and lines win
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ranked {
+    pub score: i64,
+    pub file: usize,
+    pub path: PathBuf,
+    pub line_number: usize,
+    pub line: String,
+    pub positions: Vec<usize>,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Ranked) -> Ordering {
+        self.score
+            .cmp(&other.score)
+            .then(other.file.cmp(&self.file))
+            .then(other.line_number.cmp(&self.line_number))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Ranked) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/*
+  The best `limit` lines seen so far, only
+  they are kept in memory, not all matched lines
+*/
+#[derive(Debug)]
+pub struct TopLines {
+    limit: usize,
+    // The worst kept line is on the top of the heap
+    heap: BinaryHeap<Reverse<Ranked>>,
+}
+
+impl TopLines {
+    pub fn new(limit: usize) -> TopLines {
+        TopLines {
+            limit,
+            heap: BinaryHeap::with_capacity(limit + 1),
+        }
+    }
+
+    pub fn push(&mut self, ranked: Ranked) {
+        self.heap.push(Reverse(ranked));
+
+        if self.heap.len() > self.limit {
+            self.heap.pop();
+        }
+    }
+
+    // Kept lines, the best one first
+    pub fn into_sorted(self) -> Vec<Ranked> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ranked)| ranked)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subsequence_is_required() {
+        assert!(fuzzy_match("cfg", "let config = 1;", false).is_some());
+        assert!(fuzzy_match("gfc", "let config = 1;", false).is_none());
+        assert!(fuzzy_match("Cfg", "let config = 1;", true).is_none());
+    }
+
+    #[test]
+    fn word_starts_and_consecutive_characters_win() {
+        let word_starts = fuzzy_match("sr", "search_reader", false).unwrap();
+        let inside = fuzzy_match("sr", "user", false).unwrap();
+        let camel = fuzzy_match("sr", "searchReader", false).unwrap();
+
+        assert_eq!(vec![0, 7], word_starts.positions);
+        assert_eq!(vec![0, 6], camel.positions);
+        assert!(word_starts.score > inside.score);
+        assert!(camel.score > inside.score);
+
+        let together = fuzzy_match("con", "a config", false).unwrap();
+        let apart = fuzzy_match("con", "a cxoxn", false).unwrap();
+        assert_eq!(vec![2, 3, 4], together.positions);
+        assert!(together.score > apart.score);
+    }
+
+    #[test]
+    fn best_alignment_is_found() {
+        // Greedy alignment would take `a` in `fake`, but word starts are better
+        let found = fuzzy_match("ab", "fake a_b", false).unwrap();
+
+        assert_eq!(vec![5, 7], found.positions);
+    }
+
+    #[test]
+    fn long_line_is_matched_without_large_table() {
+        let line = format!("{}config{}", "x".repeat(100_000), "y".repeat(100_000));
+        let found = fuzzy_match("cfg", &line, false).unwrap();
+
+        assert_eq!(vec![100_000, 100_003, 100_005], found.positions);
+
+        // Score is counted the same way, as for short lines
+        let short = fuzzy_match("cfg", "xconfig", false).unwrap();
+        assert_eq!(vec![1, 4, 6], short.positions);
+        assert_eq!(short.score, found.score);
+        assert_eq!(
+            short.score,
+            alignment_score(&[1, 4, 6], &[8, 0, 0, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn top_lines_keep_the_best() {
+        let mut top = TopLines::new(2);
+
+        for (line_number, score) in [(1, 10), (2, 30), (3, 20), (4, 30)] {
+            top.push(Ranked {
+                score,
+                file: 0,
+                path: PathBuf::from("a.txt"),
+                line_number,
+                line: String::new(),
+                positions: Vec::new(),
+            });
+        }
+
+        let lines: Vec<(usize, i64)> = top
+            .into_sorted()
+            .iter()
+            .map(|ranked| (ranked.line_number, ranked.score))
+            .collect();
+        assert_eq!(vec![(2, 30), (4, 30)], lines);
+    }
+}
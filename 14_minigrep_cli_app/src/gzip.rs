@@ -0,0 +1,624 @@
+// Gzip files (RFC 1952) with DEFLATE data (RFC 1951), decompressed while they are read
+
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+
+// First bytes of every gzip member: magic number and DEFLATE method
+const MAGIC: [u8; 3] = [0x1f, 0x8b, 0x08];
+
+// Header flags, which add optional fields after the fixed part
+const FLAG_HEADER_CRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+const FLAG_RESERVED: u8 = 0xe0;
+
+// Back references never reach further than this
+const WINDOW_SIZE: usize = 32 * 1024;
+// Amount of bytes decoded at once, before they are returned
+const CHUNK_SIZE: usize = 16 * 1024;
+const MAX_BITS: usize = 15;
+
+// Base lengths and extra bits of length symbols 257..=285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+// Base distances and extra bits of distance symbols 0..=29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// Order, in which lengths of the code length code are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut n = 0;
+
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+
+    table
+}
+
+// CRC-32 of the bytes, continued from the previous `crc` (0 at the start)
+pub fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let crc = bytes.iter().fold(!crc, |crc, byte| {
+        CRC_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    });
+    !crc
+}
+
+// Gzip data is found by its magic number, not by the file extension
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/*
+  Reader of the input contents: gzip data is decompressed,
+  everything else is read as it is
+*/
+pub fn decompressed<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    if is_gzip(reader.fill_buf()?) {
+        Ok(Box::new(BufReader::new(GzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/*
+  Reader of single bits, the first bit
+  is the lowest one of the byte (as DEFLATE stores them)
+*/
+#[derive(Debug)]
+struct BitReader<R> {
+    inner: R,
+    bits: u32,
+    count: u8,
+}
+
+impl<R: BufRead> BitReader<R> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let byte = match self.inner.fill_buf()?.first() {
+            Some(byte) => *byte,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "compressed data is truncated",
+                ))
+            }
+        };
+        self.inner.consume(1);
+        Ok(byte)
+    }
+
+    fn bits(&mut self, count: u8) -> io::Result<u32> {
+        while self.count < count {
+            self.bits |= (self.byte()? as u32) << self.count;
+            self.count += 8;
+        }
+
+        let value = self.bits & ((1 << count) - 1);
+        self.bits >>= count;
+        self.count -= count;
+        Ok(value)
+    }
+
+    // Skip the rest of the current byte, stored data starts on the byte boundary
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes([self.byte()?, self.byte()?]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes([
+            self.byte()?,
+            self.byte()?,
+            self.byte()?,
+            self.byte()?,
+        ]))
+    }
+
+    // More data after the member could be only the next member
+    fn has_member(&mut self) -> io::Result<bool> {
+        Ok(is_gzip(self.inner.fill_buf()?))
+    }
+}
+
+/*
+  Canonical Huffman code: amount of codes of every length,
+  and symbols ordered by their codes
+*/
+#[derive(Debug)]
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    // Build code from code lengths of symbols, `0` means symbol isn't used
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut counts = [0; MAX_BITS + 1];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Code can be incomplete (e.g. single distance code), but not over-subscribed
+        let mut left: i32 = 1;
+        for count in &counts[1..] {
+            left = (left << 1) - *count as i32;
+            if left < 0 {
+                return Err(invalid("invalid Huffman code lengths"));
+            }
+        }
+
+        let mut offsets = [0; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length] as usize;
+        }
+
+        let mut symbols = vec![0; offsets[MAX_BITS + 1]];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize]] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    // Read the code bit by bit, codes are stored from the highest bit
+    fn decode<R: BufRead>(&self, input: &mut BitReader<R>) -> io::Result<u16> {
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+
+        for count in &self.counts[1..] {
+            code |= input.bits(1)? as usize;
+            let count = *count as usize;
+
+            if code < first + count {
+                return Ok(self.symbols[index + code - first]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+// Part of the gzip stream, which is read next
+#[derive(Debug)]
+enum State {
+    Header,
+    BlockHeader,
+    Stored(usize),
+    Codes {
+        literals: Huffman,
+        distances: Huffman,
+    },
+    Trailer,
+    Done,
+}
+
+/*
+  Streaming gzip decoder: data is decompressed by chunks,
+  and only the window of the last 32 KiB is kept in memory.
+  Several members one after another are read as one stream
+*/
+#[derive(Debug)]
+pub struct GzDecoder<R> {
+    input: BitReader<R>,
+    state: State,
+    last_block: bool,
+    // Decompressed bytes, the start is kept for back references
+    window: Vec<u8>,
+    // Start of bytes in the window, which aren't returned yet
+    unread: usize,
+    crc: u32,
+    size: u32,
+}
+
+impl<R: BufRead> GzDecoder<R> {
+    pub fn new(inner: R) -> GzDecoder<R> {
+        GzDecoder {
+            input: BitReader {
+                inner,
+                bits: 0,
+                count: 0,
+            },
+            state: State::Header,
+            last_block: false,
+            window: Vec::with_capacity(WINDOW_SIZE + CHUNK_SIZE),
+            unread: 0,
+            crc: 0,
+            size: 0,
+        }
+    }
+
+    // Decode the next part of the stream, it is called only when everything is returned
+    fn step(&mut self) -> io::Result<()> {
+        self.state = match mem::replace(&mut self.state, State::Done) {
+            State::Header => {
+                self.read_header()?;
+                State::BlockHeader
+            }
+            State::BlockHeader => self.read_block_header()?,
+            State::Stored(remaining) => {
+                let len = remaining.min(CHUNK_SIZE);
+                for _ in 0..len {
+                    let byte = self.input.byte()?;
+                    self.window.push(byte);
+                }
+
+                if remaining > len {
+                    State::Stored(remaining - len)
+                } else {
+                    self.block_end()
+                }
+            }
+            State::Codes {
+                literals,
+                distances,
+            } => {
+                if self.decode_codes(&literals, &distances)? {
+                    self.block_end()
+                } else {
+                    State::Codes {
+                        literals,
+                        distances,
+                    }
+                }
+            }
+            State::Trailer => {
+                self.input.align();
+                let crc = self.input.u32()?;
+                let size = self.input.u32()?;
+
+                if crc != self.crc {
+                    return Err(invalid("CRC of decompressed data doesn't match"));
+                }
+                if size != self.size {
+                    return Err(invalid("size of decompressed data doesn't match"));
+                }
+
+                if self.input.has_member()? {
+                    State::Header
+                } else {
+                    State::Done
+                }
+            }
+            State::Done => State::Done,
+        };
+
+        Ok(())
+    }
+
+    // Skip member header, its fields (like original name) aren't needed
+    fn read_header(&mut self) -> io::Result<()> {
+        let mut fixed = [0; 10];
+        for byte in &mut fixed {
+            *byte = self.input.byte()?;
+        }
+
+        if !is_gzip(&fixed) {
+            return Err(invalid("not a gzip file"));
+        }
+        let flags = fixed[3];
+        if flags & FLAG_RESERVED != 0 {
+            return Err(invalid("unknown gzip header flags"));
+        }
+
+        if flags & FLAG_EXTRA != 0 {
+            let len = self.input.u16()?;
+            for _ in 0..len {
+                self.input.byte()?;
+            }
+        }
+        for flag in [FLAG_NAME, FLAG_COMMENT] {
+            if flags & flag != 0 {
+                while self.input.byte()? != 0 {}
+            }
+        }
+        if flags & FLAG_HEADER_CRC != 0 {
+            self.input.u16()?;
+        }
+
+        self.crc = 0;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn read_block_header(&mut self) -> io::Result<State> {
+        self.last_block = self.input.bits(1)? == 1;
+
+        match self.input.bits(2)? {
+            0 => {
+                self.input.align();
+                let len = self.input.u16()?;
+                let complement = self.input.u16()?;
+
+                if len != !complement {
+                    return Err(invalid("invalid length of stored block"));
+                }
+                Ok(State::Stored(len as usize))
+            }
+            1 => {
+                let mut lengths = [0; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+
+                Ok(State::Codes {
+                    literals: Huffman::new(&lengths)?,
+                    distances: Huffman::new(&[5; 30])?,
+                })
+            }
+            2 => self.read_dynamic_codes(),
+            _ => Err(invalid("invalid block type")),
+        }
+    }
+
+    // Codes of the block are stored before it, compressed with one more Huffman code
+    fn read_dynamic_codes(&mut self) -> io::Result<State> {
+        let literals_len = self.input.bits(5)? as usize + 257;
+        let distances_len = self.input.bits(5)? as usize + 1;
+        let code_lengths_len = self.input.bits(4)? as usize + 4;
+
+        let mut code_lengths = [0; 19];
+        for index in &CODE_LENGTH_ORDER[..code_lengths_len] {
+            code_lengths[*index] = self.input.bits(3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_lengths)?;
+
+        let mut lengths = Vec::with_capacity(literals_len + distances_len);
+        while lengths.len() < literals_len + distances_len {
+            let (length, repeat) = match code_lengths.decode(&mut self.input)? {
+                length @ 0..=15 => (length as u8, 1),
+                16 => match lengths.last() {
+                    Some(previous) => (*previous, 3 + self.input.bits(2)?),
+                    None => return Err(invalid("repeated code length without previous one")),
+                },
+                17 => (0, 3 + self.input.bits(3)?),
+                _ => (0, 11 + self.input.bits(7)?),
+            };
+
+            if lengths.len() + repeat as usize > literals_len + distances_len {
+                return Err(invalid("too many code lengths"));
+            }
+            lengths.extend((0..repeat).map(|_| length));
+        }
+
+        if lengths[256] == 0 {
+            return Err(invalid("missing end of block code"));
+        }
+
+        Ok(State::Codes {
+            literals: Huffman::new(&lengths[..literals_len])?,
+            distances: Huffman::new(&lengths[literals_len..])?,
+        })
+    }
+
+    /*
+      Decode literals and back references until the chunk is full,
+      returns `true` when the end of the block is reached
+    */
+    fn decode_codes(&mut self, literals: &Huffman, distances: &Huffman) -> io::Result<bool> {
+        let chunk_end = self.window.len() + CHUNK_SIZE;
+
+        while self.window.len() < chunk_end {
+            let symbol = literals.decode(&mut self.input)? as usize;
+
+            if symbol < 256 {
+                self.window.push(symbol as u8);
+                continue;
+            }
+            if symbol == 256 {
+                return Ok(true);
+            }
+
+            let index = symbol - 257;
+            if index >= LENGTH_BASE.len() {
+                return Err(invalid("invalid length code"));
+            }
+            let length =
+                LENGTH_BASE[index] as usize + self.input.bits(LENGTH_EXTRA[index])? as usize;
+
+            let index = distances.decode(&mut self.input)? as usize;
+            if index >= DISTANCE_BASE.len() {
+                return Err(invalid("invalid distance code"));
+            }
+            let distance =
+                DISTANCE_BASE[index] as usize + self.input.bits(DISTANCE_EXTRA[index])? as usize;
+
+            if distance > self.window.len() {
+                return Err(invalid("distance is too far back"));
+            }
+
+            // Copy byte by byte, since the copied bytes could overlap the new ones
+            let start = self.window.len() - distance;
+            for offset in 0..length {
+                let byte = self.window[start + offset];
+                self.window.push(byte);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn block_end(&self) -> State {
+        if self.last_block {
+            State::Trailer
+        } else {
+            State::BlockHeader
+        }
+    }
+
+    // Drop returned bytes, which are too far back for references
+    fn compact(&mut self) {
+        if self.window.len() > 2 * WINDOW_SIZE {
+            let drop = self.window.len() - WINDOW_SIZE;
+            self.window.drain(..drop);
+            self.unread -= drop;
+        }
+    }
+}
+
+impl<R: BufRead> Read for GzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.unread < self.window.len() {
+                let len = buf.len().min(self.window.len() - self.unread);
+                let bytes = &self.window[self.unread..self.unread + len];
+
+                buf[..len].copy_from_slice(bytes);
+                self.crc = crc32(self.crc, bytes);
+                self.size = self.size.wrapping_add(len as u32);
+                self.unread += len;
+                return Ok(len);
+            }
+
+            if let State::Done = self.state {
+                return Ok(0);
+            }
+
+            self.compact();
+            self.step()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `hello hello hello\n`, compressed with fixed Huffman codes
+    const FIXED: [u8; 29] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9,
+        0x57, 0xc8, 0x40, 0x90, 0x5c, 0x00, 0x3b, 0x7c, 0x8a, 0xdf, 0x12, 0x00, 0x00, 0x00,
+    ];
+
+    // Words without order, compressed with dynamic Huffman codes
+    const DYNAMIC: [u8; 78] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x2d, 0x8a, 0x41, 0x0a, 0x00,
+        0x20, 0x08, 0x04, 0xef, 0xbd, 0xc2, 0xaf, 0x05, 0x15, 0x48, 0xa0, 0x60, 0x41, 0xdf, 0xcf,
+        0x89, 0x40, 0xdd, 0x65, 0x46, 0xb5, 0xe1, 0x4d, 0xd7, 0x64, 0xf5, 0xf7, 0x53, 0xc3, 0xc8,
+        0x1e, 0xe1, 0x41, 0x11, 0x9f, 0x25, 0x07, 0x8f, 0xcb, 0x4a, 0x3c, 0x0d, 0x63, 0x85, 0xf3,
+        0x45, 0x86, 0xf5, 0x0d, 0x78, 0x2f, 0x02, 0x4b, 0x70, 0x01, 0xe9, 0xda, 0x98, 0x8f, 0x6b,
+        0x00, 0x00, 0x00,
+    ];
+
+    fn decompress(bytes: &[u8]) -> io::Result<String> {
+        let mut contents = String::new();
+        decompressed(bytes)?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    // Member with a stored block and original file name in the header
+    fn stored_member(data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u16;
+
+        [
+            &[0x1f, 0x8b, 0x08, FLAG_NAME, 0, 0, 0, 0, 0, 0xff][..],
+            b"log.txt\0",
+            &[0x01],
+            &len.to_le_bytes(),
+            &(!len).to_le_bytes(),
+            data,
+            &crc32(0, data).to_le_bytes(),
+            &(data.len() as u32).to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn crc_of_check_string() {
+        assert_eq!(0xcbf4_3926, crc32(0, b"123456789"));
+        assert_eq!(0xcbf4_3926, crc32(crc32(0, b"1234"), b"56789"));
+    }
+
+    #[test]
+    fn blocks_and_members() {
+        assert_eq!("hello hello hello\n", decompress(&FIXED).unwrap());
+        assert_eq!(
+            "infodiskdiskinfodiskwarndiskerrordisk ok\nok\ninfowarnok\n\
+             warnerrorinfoinfo infook\nwarnok\nnetinfoerror warnnet",
+            decompress(&DYNAMIC).unwrap()
+        );
+
+        let members = [&FIXED[..], &stored_member(b"stored\n")].concat();
+        assert_eq!("hello hello hello\nstored\n", decompress(&members).unwrap());
+    }
+
+    #[test]
+    fn long_stream() {
+        // Stored blocks are limited to 64 KiB, so several members make a long stream
+        let line = "0123456789 abcdefghijklmnopqrstuvwxyz\n".repeat(1500);
+        let members = [
+            stored_member(line.as_bytes()),
+            stored_member(line.as_bytes()),
+        ]
+        .concat();
+
+        assert_eq!(line.repeat(2), decompress(&members).unwrap());
+    }
+
+    #[test]
+    fn plain_text_is_kept() {
+        assert_eq!("\x1f plain\n", decompress(b"\x1f plain\n").unwrap());
+    }
+
+    #[test]
+    fn corrupted_data() {
+        let mut corrupted = FIXED;
+        corrupted[22] ^= 0xff;
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            decompress(&corrupted).unwrap_err().kind()
+        );
+
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            decompress(&FIXED[..20]).unwrap_err().kind()
+        );
+    }
+}
@@ -0,0 +1,410 @@
+// Trigram index of a directory, built with `minigrep index build DIR`
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::fold::fold_str;
+use crate::replace::write_atomically;
+use crate::{gzip, walk, Config, Matcher};
+
+// Index is kept inside of the indexed directory
+pub const FILE_NAME: &str = ".minigrep-index";
+
+/*
+  Beginning of the index file with format version, zero byte
+  makes the index binary, so searches in the directory skip it
+*/
+const MAGIC: &[u8; 8] = b"MGIDX\0\x01\0";
+
+/*
+  Indexed file: path relative to the directory, modification time
+  and size, when one of them changes the file isn't trusted anymore
+*/
+#[derive(Debug, Clone, PartialEq)]
+struct FileEntry {
+    path: String,
+    modified: (u64, u32),
+    size: u64,
+}
+
+impl FileEntry {
+    fn is_fresh(&self, metadata: &Metadata) -> bool {
+        modified(metadata) == Some(self.modified) && metadata.len() == self.size
+    }
+}
+
+/*
+  Indexed files and, for every trigram (three bytes of folded text),
+  list of files, which have it in some line
+*/
+#[derive(Debug, Default, PartialEq)]
+pub struct Index {
+    files: Vec<FileEntry>,
+    trigrams: HashMap<u32, Vec<u32>>,
+}
+
+impl Index {
+    /*
+      Index every text file in the directory (ignored
+      and binary files are skipped the same way as in search)
+    */
+    pub fn build(dir: &Path) -> io::Result<Index> {
+        let mut index = Index::default();
+
+        for path in walk::files(dir)? {
+            let metadata = fs::metadata(&path)?;
+
+            // File, which can't be stamped or named, is always searched
+            let (Some(modified), Some(relative)) = (modified(&metadata), relative_path(dir, &path))
+            else {
+                continue;
+            };
+
+            // Compressed files are indexed by their decompressed contents, as they are searched
+            let mut reader = gzip::decompressed(BufReader::new(File::open(&path)?))?;
+            if walk::is_binary(reader.fill_buf()?) {
+                continue;
+            }
+
+            let file_id = index.files.len() as u32;
+            for trigram in file_trigrams(reader)? {
+                index.trigrams.entry(trigram).or_default().push(file_id);
+            }
+
+            index.files.push(FileEntry {
+                path: relative,
+                modified,
+                size: metadata.len(),
+            });
+        }
+
+        Ok(index)
+    }
+
+    // Write index into the directory, so it replaces the old one at once
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        write_atomically(&dir.join(FILE_NAME), &self.encode())
+    }
+
+    // Read index of the directory, `None` when it wasn't built
+    pub fn load(dir: &Path) -> io::Result<Option<Index>> {
+        match fs::read(dir.join(FILE_NAME)) {
+            Ok(bytes) => Index::decode(&bytes).map(Some),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /*
+      Keep only files, which could have a line with one of the patterns,
+      files, which are new or changed after the index was built, are kept too
+    */
+    pub fn candidates(&self, dir: &Path, files: Vec<PathBuf>, patterns: &[String]) -> Vec<PathBuf> {
+        let patterns: Vec<HashSet<u32>> = patterns
+            .iter()
+            .map(|pattern| line_trigrams(pattern))
+            .collect();
+
+        // Pattern shorter than a trigram could be anywhere
+        if patterns.iter().any(|trigrams| trigrams.is_empty()) {
+            return files;
+        }
+
+        // Files, which have all trigrams of at least one pattern
+        let mut matched = HashSet::new();
+        for trigrams in &patterns {
+            let mut lists = trigrams.iter().map(|trigram| self.trigrams.get(trigram));
+            let first: HashSet<u32> = match lists.next().flatten() {
+                Some(list) => list.iter().copied().collect(),
+                None => continue,
+            };
+
+            let found = lists.try_fold(first, |found, list| {
+                let list: HashSet<u32> = list?.iter().copied().collect();
+                Some(found.intersection(&list).copied().collect::<HashSet<u32>>())
+            });
+            matched.extend(found.unwrap_or_default());
+        }
+
+        let entries: HashMap<&str, (usize, &FileEntry)> = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(id, entry)| (entry.path.as_str(), (id, entry)))
+            .collect();
+
+        files
+            .into_iter()
+            .filter(|path| {
+                let entry = relative_path(dir, path)
+                    .and_then(|relative| entries.get(relative.as_str()).copied());
+
+                match (entry, fs::metadata(path)) {
+                    (Some((id, entry)), Ok(metadata)) if entry.is_fresh(&metadata) => {
+                        matched.contains(&(id as u32))
+                    }
+                    // Stale or unknown file can't be judged by the index
+                    _ => true,
+                }
+            })
+            .collect()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+
+        bytes.extend((self.files.len() as u32).to_le_bytes());
+        for file in &self.files {
+            bytes.extend((file.path.len() as u32).to_le_bytes());
+            bytes.extend(file.path.as_bytes());
+            bytes.extend(file.modified.0.to_le_bytes());
+            bytes.extend(file.modified.1.to_le_bytes());
+            bytes.extend(file.size.to_le_bytes());
+        }
+
+        // Sorted trigrams, so the same directory always gives the same file
+        let mut trigrams: Vec<(&u32, &Vec<u32>)> = self.trigrams.iter().collect();
+        trigrams.sort();
+
+        bytes.extend((trigrams.len() as u32).to_le_bytes());
+        for (trigram, files) in trigrams {
+            bytes.extend(trigram.to_le_bytes());
+            bytes.extend((files.len() as u32).to_le_bytes());
+            for file in files {
+                bytes.extend(file.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Index> {
+        let mut reader = ByteReader { bytes, position: 0 };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("unknown format of the index"));
+        }
+
+        let mut index = Index::default();
+
+        for _ in 0..reader.u32()? {
+            let len = reader.u32()? as usize;
+            let path = String::from_utf8(reader.take(len)?.to_vec())
+                .map_err(|_| invalid("file path isn't valid UTF-8"))?;
+
+            index.files.push(FileEntry {
+                path,
+                modified: (reader.u64()?, reader.u32()?),
+                size: reader.u64()?,
+            });
+        }
+
+        for _ in 0..reader.u32()? {
+            let trigram = reader.u32()?;
+            let mut files = Vec::new();
+
+            for _ in 0..reader.u32()? {
+                let file = reader.u32()?;
+                if file as usize >= index.files.len() {
+                    return Err(invalid("unknown file in the index"));
+                }
+                files.push(file);
+            }
+
+            index.trigrams.insert(trigram, files);
+        }
+
+        Ok(index)
+    }
+}
+
+// Reader of little endian numbers, which fails on truncated data
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self.position.saturating_add(len);
+        let taken = self
+            .bytes
+            .get(self.position..end)
+            .ok_or_else(|| invalid("index is truncated"))?;
+
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut number = [0; 4];
+        number.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(number))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut number = [0; 8];
+        number.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(number))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Modification time as seconds and nanoseconds since Unix epoch
+fn modified(metadata: &Metadata) -> Option<(u64, u32)> {
+    let since_epoch = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((since_epoch.as_secs(), since_epoch.subsec_nanos()))
+}
+
+// Path inside of the directory with `/` separators, the same on every platform
+fn relative_path(dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(dir).ok()?;
+    let parts: Option<Vec<&str>> = relative.iter().map(|part| part.to_str()).collect();
+
+    Some(parts?.join("/"))
+}
+
+/*
+  Trigrams of the folded line, so both case sensitive
+  and case insensitive queries could use the same index
+*/
+fn line_trigrams(line: &str) -> HashSet<u32> {
+    fold_str(line)
+        .as_bytes()
+        .windows(3)
+        .map(|bytes| u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+        .collect()
+}
+
+/*
+  Trigrams of every line of the file, lines are read one by one,
+  trigrams never cross lines, because matches never do
+*/
+fn file_trigrams<R: BufRead>(mut reader: R) -> io::Result<HashSet<u32>> {
+    let mut trigrams = HashSet::new();
+    let mut buffer = Vec::new();
+
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+
+        let bytes = buffer.strip_suffix(b"\n").unwrap_or(&buffer);
+        let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+        trigrams.extend(line_trigrams(&String::from_utf8_lossy(bytes)));
+    }
+
+    Ok(trigrams)
+}
+
+/*
+  Narrow files of the searched directory with its index, when it exists,
+  only literal queries could use it, broken index is reported and skipped
+*/
+pub fn narrow(config: &Config, dir: &Path, files: Vec<PathBuf>) -> Vec<PathBuf> {
+    let matcher = match &config.matcher {
+        Matcher::Word(matcher) => matcher.as_ref(),
+        matcher => matcher,
+    };
+    let literal = matches!(matcher, Matcher::Literal { .. } | Matcher::Literals(_));
+
+    // Inverted and fuzzy searches select lines without the query
+    if !literal || config.invert || config.fuzzy {
+        return files;
+    }
+
+    match Index::load(dir) {
+        Ok(Some(index)) => index.candidates(dir, files, &config.patterns),
+        Ok(None) => files,
+        Err(error) => {
+            eprintln!(
+                "{}: index is skipped: {}",
+                dir.join(FILE_NAME).display(),
+                error
+            );
+            files
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn encode_and_decode() {
+        let mut index = Index::default();
+        index.files.push(FileEntry {
+            path: String::from("src/main.rs"),
+            modified: (1_700_000_000, 42),
+            size: 120,
+        });
+        index.trigrams.insert(0x616263, vec![0]);
+
+        let bytes = index.encode();
+        assert_eq!(index, Index::decode(&bytes).unwrap());
+        assert!(Index::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Index::decode(b"not an index").is_err());
+    }
+
+    #[test]
+    fn narrow_candidates() {
+        let dir = env::temp_dir().join(format!("minigrep-index-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "Rust is fast\nand safe").unwrap();
+        fs::write(dir.join("b.txt"), "slow\n").unwrap();
+        let files = walk::files(&dir).unwrap();
+
+        Index::build(&dir).unwrap().write(&dir).unwrap();
+        let index = Index::load(&dir).unwrap().unwrap();
+        assert_eq!(2, index.len());
+
+        // Index itself is binary, so it isn't indexed again
+        assert_eq!(2, Index::build(&dir).unwrap().len());
+        let patterns = |patterns: &[&str]| -> Vec<String> {
+            patterns.iter().map(|pattern| pattern.to_string()).collect()
+        };
+
+        assert_eq!(
+            vec![dir.join("a.txt")],
+            index.candidates(&dir, files.clone(), &patterns(&["RUST"]))
+        );
+        // Trigrams of different lines don't make a match
+        assert!(index
+            .candidates(&dir, files.clone(), &patterns(&["fast and"]))
+            .is_empty());
+        assert_eq!(
+            files,
+            index.candidates(&dir, files.clone(), &patterns(&["slow", "safe"]))
+        );
+        assert_eq!(
+            files,
+            index.candidates(&dir, files.clone(), &patterns(&["a"]))
+        );
+
+        // Changed file isn't trusted, until index is built again
+        fs::write(dir.join("b.txt"), "Rust is slow, but it is changed\n").unwrap();
+        assert_eq!(
+            files,
+            index.candidates(&dir, files.clone(), &patterns(&["rust"]))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
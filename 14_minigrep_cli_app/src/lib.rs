@@ -1,31 +1,284 @@
 // Root of library crate
 
 use std::error::Error;
-use std::{env, fs};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::{env, fmt, thread};
+
+pub mod aho_corasick;
+pub mod args;
+pub mod context;
+pub mod fold;
+pub mod fuzzy;
+pub mod gzip;
+pub mod index;
+pub mod json;
+pub mod output;
+pub mod pool;
+pub mod rc;
+pub mod regex;
+pub mod replace;
+pub mod walk;
+pub mod watch;
+
+use aho_corasick::AhoCorasick;
+use args::{Arg, ArgParser};
+use context::{ContextTracker, Line};
+use fuzzy::{Ranked, TopLines};
+use output::{ColorChoice, OutputFormat, Summary};
+use pool::Streamed;
+use regex::{Regex, RegexError};
+use replace::Replacement;
+use unicode_segmentation::UnicodeSegmentation;
 
 /*
     Function to run, read and manipulate with
     file contents, takes a config as parameter
 */
 pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    // Get file contents
-    let contents: String = fs::read_to_string(config.filename)?;
+    let mut inputs = Vec::new();
+
+    for filename in &config.filenames {
+        let path = Path::new(filename);
+
+        // Directory is searched recursively, binary files inside of it are skipped,
+        // files without the query are skipped too, when directory has an index
+        // (but not in watch mode, the query could be added to any of them later)
+        if path.is_dir() {
+            let files = walk::files(path)?;
+            let files = if config.watch {
+                files
+            } else {
+                index::narrow(&config, path, files)
+            };
+
+            for file in files {
+                inputs.push(Input {
+                    path: file,
+                    skip_binary: true,
+                });
+            }
+        } else {
+            inputs.push(Input {
+                path: path.to_path_buf(),
+                skip_binary: false,
+            });
+        }
+    }
+
+    // Every match is prefixed with a file path, when there could be more than one file
+    let with_path =
+        config.filenames.len() > 1 || config.filenames.iter().any(|name| Path::new(name).is_dir());
+
+    // Colors are decided by config, not by the library guess
+    colored::control::set_override(config.color.enabled());
+
+    if config.fuzzy {
+        return run_fuzzy(&config, &inputs, with_path);
+    }
+
+    // Watch mode never ends, it prints lines, which are added to the files
+    if config.watch {
+        return Ok(watch::run(&config, &inputs, with_path)?);
+    }
+
+    let mut summary = Summary::default();
+    let mut failed = 0;
+    let mut out = io::stdout().lock();
 
-    // Check for case sensitive
-    let resulsts = if config.case_sensitive {
-        search(&config.query, &contents)
+    if inputs.len() == 1 || config.jobs == 1 {
+        // Write straight to output, so nothing is kept in memory
+        for input in &inputs {
+            match search_input(&config, input, with_path, &mut out) {
+                Ok(matches) => summary.add(matches),
+                Err(error) => {
+                    eprintln!("{}: {}", input.path.display(), error);
+                    failed += 1;
+                }
+            }
+        }
     } else {
-        search_case_insensitive(&config.query, &contents)
-    };
+        // Output of every file is handed over by chunks, and printed in order of files,
+        // so only a few chunks of every file are kept in memory
+        let mut written = Ok(());
+
+        pool::for_each_streamed(
+            &inputs,
+            config.jobs,
+            |input, chunks| search_input(&config, input, with_path, chunks),
+            |input, message| match message {
+                Streamed::Chunk(chunk) => {
+                    if written.is_ok() {
+                        written = out.write_all(&chunk);
+                    }
+                }
+                Streamed::Done(Ok(matches)) => summary.add(matches),
+                Streamed::Done(Err(error)) => {
+                    eprintln!("{}: {}", input.path.display(), error);
+                    failed += 1;
+                }
+            },
+        );
+
+        written?;
+    }
+
+    if config.output == OutputFormat::Json {
+        output::print_summary(&summary, &mut out)?;
+    }
 
-    // Print all lines, that contains a provided query
-    for line in resulsts {
-        println!("{}", line);
+    if failed > 0 {
+        return Err(format!("{} file(s) couldn't be searched", failed).into());
     }
 
     Ok(())
 }
 
+/*
+  Fuzzy search: lines of all inputs are ranked together,
+  and only the best `top` of them are printed
+*/
+fn run_fuzzy(config: &Config, inputs: &[Input], with_path: bool) -> Result<(), Box<dyn Error>> {
+    let mut top = TopLines::new(config.top);
+    let mut file = 0;
+    let mut failed = 0;
+
+    pool::for_each_ordered(
+        inputs,
+        config.jobs,
+        |input| rank_input(config, input),
+        |input, result| {
+            match result {
+                Ok(lines) => {
+                    // Order of files is known only here, it breaks ties between equal scores
+                    for mut ranked in lines {
+                        ranked.file = file;
+                        top.push(ranked);
+                    }
+                }
+                Err(error) => {
+                    eprintln!("{}: {}", input.path.display(), error);
+                    failed += 1;
+                }
+            }
+            file += 1;
+        },
+    );
+
+    output::print_ranked(
+        config,
+        &top.into_sorted(),
+        with_path,
+        &mut io::stdout().lock(),
+    )?;
+
+    if failed > 0 {
+        return Err(format!("{} file(s) couldn't be searched", failed).into());
+    }
+
+    Ok(())
+}
+
+/*
+  Score every line of one input against every query,
+  returns the best `top` lines of the input
+*/
+fn rank_input(config: &Config, input: &Input) -> io::Result<Vec<Ranked>> {
+    let (path, mut reader): (PathBuf, Box<dyn BufRead>) = if input.path == Path::new("-") {
+        (
+            PathBuf::from("(standard input)"),
+            gzip::decompressed(io::stdin().lock())?,
+        )
+    } else {
+        let mut reader = gzip::decompressed(BufReader::new(File::open(&input.path)?))?;
+
+        if input.skip_binary && walk::is_binary(reader.fill_buf()?) {
+            return Ok(Vec::new());
+        }
+        (input.path.clone(), reader)
+    };
+
+    let mut top = TopLines::new(config.top);
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let bytes = buffer.strip_suffix(b"\n").unwrap_or(&buffer);
+        let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+        let line = String::from_utf8_lossy(bytes);
+
+        // The best of all queries counts
+        let found = config
+            .patterns
+            .iter()
+            .filter_map(|query| {
+                let case_sensitive = config.case_sensitive && fuzzy::smart_case_sensitive(query);
+                fuzzy::fuzzy_match(query, &line, case_sensitive)
+            })
+            .max_by_key(|found| found.score);
+
+        if let Some(found) = found {
+            top.push(Ranked {
+                score: found.score,
+                file: 0,
+                path: path.clone(),
+                line_number,
+                line: line.into_owned(),
+                positions: found.positions,
+            });
+        }
+    }
+
+    Ok(top.into_sorted())
+}
+
+/*
+  Single file to search, `-` stands for standard input
+*/
+struct Input {
+    path: PathBuf,
+    skip_binary: bool,
+}
+
+/*
+  Function to search one input and write results to `out`,
+  binary files found inside of directories are skipped,
+  returns amount of matched lines
+*/
+fn search_input<W: Write>(
+    config: &Config,
+    input: &Input,
+    with_path: bool,
+    out: &mut W,
+) -> io::Result<usize> {
+    if let Some(replacement) = &config.replace {
+        return replace::replace_input(config, replacement, &input.path, input.skip_binary, out);
+    }
+
+    if input.path == Path::new("-") {
+        let path = Path::new("(standard input)");
+        let reader = gzip::decompressed(io::stdin().lock())?;
+        return output::print_results(config, path, with_path, reader, out);
+    }
+
+    // File is read line by line, so it could be bigger than memory (compressed one too)
+    let mut reader = gzip::decompressed(BufReader::new(File::open(&input.path)?))?;
+
+    // Only the first block is checked, the rest is never loaded at once
+    if input.skip_binary && walk::is_binary(reader.fill_buf()?) {
+        return Ok(0);
+    }
+
+    output::print_results(config, &input.path, with_path, reader, out)
+}
+
 /*
   Function to search the contents in the file,
   using query from function parameters
@@ -43,24 +296,683 @@ pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
   using query from function parameters (case insensitivly)
 */
 pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let query = query.to_lowercase();
+    // Fold only query, lines are folded character by character without allocation
+    let query = fold::fold_str(query);
 
     // Simplify function using collection methods
     contents
         .lines()
-        .filter(|line| line.to_lowercase().contains(&query))
+        .filter(|line| fold::find_at(line, &query, 0).is_some())
+        .collect()
+}
+
+/*
+  Function to search the contents in the file, using fuzzy matching:
+  characters of query must be in the line in the same order, but not together,
+  returns at most `limit` lines, which are matched best (best first)
+*/
+pub fn search_fuzzy<'a>(query: &str, contents: &'a str, limit: usize) -> Vec<&'a str> {
+    let case_sensitive = fuzzy::smart_case_sensitive(query);
+
+    let mut found: Vec<(i64, &str)> = contents
+        .lines()
+        .filter_map(|line| {
+            fuzzy::fuzzy_match(query, line, case_sensitive).map(|found| (found.score, line))
+        })
+        .collect();
+
+    // Sort is stable, so lines with equal score keep their order
+    found.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    found
+        .into_iter()
+        .take(limit)
+        .map(|(_, line)| line)
+        .collect()
+}
+
+/*
+  Function to search the contents in the file,
+  using compiled regular expression
+*/
+pub fn search_regex<'a>(regex: &Regex, contents: &'a str) -> Vec<&'a str> {
+    contents
+        .lines()
+        .filter(|line| regex.is_match(line))
+        .collect()
+}
+
+/*
+  Function to search the contents in the file,
+  using one of the search functions above, depends on matcher
+*/
+pub fn search_with<'a>(matcher: &Matcher, contents: &'a str) -> Vec<&'a str> {
+    match matcher {
+        Matcher::Literal {
+            query,
+            case_sensitive: true,
+        } => search(query, contents),
+        Matcher::Literal {
+            query,
+            case_sensitive: false,
+        } => search_case_insensitive(query, contents),
+        Matcher::Regex(regex) => search_regex(regex, contents),
+        Matcher::Literals(_) | Matcher::Regexes { .. } | Matcher::Word(_) => contents
+            .lines()
+            .filter(|line| matcher.is_match(line))
+            .collect(),
+    }
+}
+
+/*
+  Matched line with its position, so editors could jump to it:
+  line number and column start from 1, byte offset is counted
+  from the beginning of contents to the first match in the line,
+  submatches are byte ranges of every match inside of the line,
+  patterns are indexes of the matched pattern for every submatch
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    pub line_number: usize,
+    pub column: usize,
+    pub byte_offset: usize,
+    pub line: &'a str,
+    pub submatches: Vec<(usize, usize)>,
+    pub patterns: Vec<usize>,
+}
+
+/*
+  Function to search the contents in the file,
+  using all options from config (matcher and inverted match),
+  returns match records with positions of the found lines
+*/
+pub fn search_matches<'a>(config: &Config, contents: &'a str) -> Vec<Match<'a>> {
+    lines_with_offsets(contents)
+        .enumerate()
+        .filter_map(|(index, (line_offset, line))| match_line(config, index + 1, line_offset, line))
+        .collect()
+}
+
+/*
+  Function to search any reader (file, standard input) line by line,
+  so used memory is bounded by the longest line instead of the whole size.
+  Invalid UTF-8 is replaced with `\u{FFFD}`, every output line is passed
+  to `sink` (nothing is passed in count mode), returns amount of matched lines
+*/
+pub fn search_reader<R, F>(config: &Config, mut reader: R, mut sink: F) -> io::Result<usize>
+where
+    R: BufRead,
+    F: FnMut(Line) -> io::Result<()>,
+{
+    let mut tracker: ContextTracker<String> =
+        ContextTracker::new(config.before_context, config.after_context);
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+    let mut line_offset = 0;
+    let mut count = 0;
+
+    loop {
+        buffer.clear();
+        let read = reader.read_until(b'\n', &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let bytes = buffer.strip_suffix(b"\n").unwrap_or(&buffer);
+        let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+        let line = String::from_utf8_lossy(bytes);
+
+        match match_line(config, line_number, line_offset, &line) {
+            Some(found) => {
+                count += 1;
+
+                if !config.count {
+                    let (separator, before) = tracker.matched_line(line_number);
+
+                    if separator {
+                        sink(Line::Separator)?;
+                    }
+                    for (line_number, line) in &before {
+                        sink(Line::Context {
+                            line_number: *line_number,
+                            line,
+                        })?;
+                    }
+                    sink(Line::Match(found))?;
+                }
+            }
+            None if !config.count && tracker.keeps(line_number) => {
+                if let Some((line_number, line)) =
+                    tracker.context_line(line_number, line.into_owned())
+                {
+                    sink(Line::Context {
+                        line_number,
+                        line: &line,
+                    })?;
+                }
+            }
+            None => {}
+        }
+
+        line_offset += read;
+    }
+
+    Ok(count)
+}
+
+/*
+  Check single line, returns match record for it,
+  when it is selected (taking inverted match into account)
+*/
+pub(crate) fn match_line<'a>(
+    config: &Config,
+    line_number: usize,
+    line_offset: usize,
+    line: &'a str,
+) -> Option<Match<'a>> {
+    let (submatches, patterns) = config
+        .matcher
+        .find_all_patterns(line)
+        .into_iter()
+        .map(|(start, end, pattern)| ((start, end), pattern))
+        .unzip::<_, _, Vec<(usize, usize)>, Vec<usize>>();
+
+    if submatches.is_empty() != config.invert {
+        return None;
+    }
+
+    // Inverted match has nothing found, so it points to the line start
+    let start = submatches.first().map_or(0, |(start, _)| *start);
+
+    Some(Match {
+        line_number,
+        column: line[..start].chars().count() + 1,
+        byte_offset: line_offset + start,
+        line,
+        submatches,
+        patterns,
+    })
+}
+
+/*
+  Iterate over lines (the same way as `str::lines` does)
+  together with the byte offset of each line start
+*/
+pub fn lines_with_offsets(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+
+    contents.split_inclusive('\n').map(move |line| {
+        let start = offset;
+        offset += line.len();
+
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        (start, line)
+    })
+}
+
+/*
+  Enum of the ways to match a line,
+  regex is compiled once and reused for every line,
+  several patterns are matched in one pass over the line,
+  `Word` keeps only matches of inner matcher, which are whole words
+*/
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    Literal { query: String, case_sensitive: bool },
+    Regex(Regex),
+    Literals(AhoCorasick),
+    Regexes { regex: Regex, groups: Vec<usize> },
+    Word(Box<Matcher>),
+}
+
+impl Matcher {
+    // Create matcher for the single query
+    pub fn new(
+        query: &str,
+        regex: bool,
+        word: bool,
+        case_sensitive: bool,
+    ) -> Result<Matcher, RegexError> {
+        Matcher::with_patterns(&[query.to_string()], regex, word, case_sensitive)
+    }
+
+    /*
+      Create matcher for the list of patterns, whole word search
+      checks Unicode word boundaries around every match
+    */
+    pub fn with_patterns(
+        patterns: &[String],
+        regex: bool,
+        word: bool,
+        case_sensitive: bool,
+    ) -> Result<Matcher, RegexError> {
+        let matcher = match (patterns, regex) {
+            ([query], true) => Matcher::Regex(Regex::new(query, case_sensitive)?),
+            ([query], false) => {
+                // Fold query once, instead of doing it for every line
+                let query = if case_sensitive {
+                    query.to_string()
+                } else {
+                    fold::fold_str(query)
+                };
+
+                Matcher::Literal {
+                    query,
+                    case_sensitive,
+                }
+            }
+            (patterns, true) => {
+                // Every pattern becomes a group of one alternation, so errors point into pattern itself
+                let mut groups = Vec::with_capacity(patterns.len());
+                let mut group = 1;
+
+                for pattern in patterns {
+                    groups.push(group);
+                    group += Regex::new(pattern, case_sensitive)?.group_count() + 1;
+                }
+
+                let alternation: Vec<String> = patterns
+                    .iter()
+                    .map(|pattern| format!("({})", pattern))
+                    .collect();
+                let regex = Regex::new(&alternation.join("|"), case_sensitive)?;
+
+                Matcher::Regexes { regex, groups }
+            }
+            (patterns, false) => Matcher::Literals(AhoCorasick::new(patterns, case_sensitive)),
+        };
+
+        if word {
+            Ok(Matcher::Word(Box::new(matcher)))
+        } else {
+            Ok(matcher)
+        }
+    }
+
+    // Check if a single line is matched
+    pub fn is_match(&self, line: &str) -> bool {
+        self.find_at(line, 0).is_some()
+    }
+
+    /*
+      Find byte ranges of all non overlapping matches in the line,
+      empty matches (e.g. of `x*`) count for `is_match`, but aren't listed
+    */
+    pub fn find_all(&self, line: &str) -> Vec<(usize, usize)> {
+        self.find_all_patterns(line)
+            .into_iter()
+            .map(|(start, end, _)| (start, end))
+            .collect()
+    }
+
+    /*
+      The same as `find_all`, but every match
+      has index of the pattern, which is matched
+    */
+    pub fn find_all_patterns(&self, line: &str) -> Vec<(usize, usize, usize)> {
+        let mut found = Vec::new();
+        let mut first_empty = None;
+        let mut position = 0;
+
+        while let Some((start, end, pattern)) = self.find_pattern_at(line, position) {
+            if start < end {
+                found.push((start, end, pattern));
+                position = end;
+                continue;
+            }
+
+            first_empty = first_empty.or(Some((start, end, pattern)));
+
+            // Empty match must move forward, otherwise we loop forever
+            match line[end..].chars().next() {
+                Some(c) => position = end + c.len_utf8(),
+                None => break,
+            }
+        }
+
+        // Only empty matches are in the line, keep one, so line is still matched
+        if found.is_empty() {
+            found.extend(first_empty);
+        }
+
+        found
+    }
+
+    // Find byte range of the first match, which starts at `start` or later
+    pub fn find_at(&self, line: &str, start: usize) -> Option<(usize, usize)> {
+        self.find_pattern_at(line, start)
+            .map(|(start, end, _)| (start, end))
+    }
+
+    /*
+      Find the first match, which starts at `start` or later, and return
+      byte ranges of the whole match and of every group of the matched pattern,
+      literal patterns have no groups
+    */
+    pub fn captures_at(&self, line: &str, start: usize) -> Option<Vec<Option<(usize, usize)>>> {
+        match self {
+            Matcher::Regex(regex) => {
+                let captures = regex.captures_at(line, start)?;
+                Some(
+                    (0..captures.len())
+                        .map(|group| captures.get(group))
+                        .collect(),
+                )
+            }
+            Matcher::Regexes { regex, groups } => {
+                let captures = regex.captures_at(line, start)?;
+                let pattern = groups
+                    .iter()
+                    .position(|group| captures.get(*group).is_some())?;
+
+                // Groups of the pattern lie between its own group and the next pattern
+                let first = groups[pattern] + 1;
+                let last = groups.get(pattern + 1).copied().unwrap_or(captures.len());
+
+                let whole = std::iter::once(captures.get(0));
+                Some(
+                    whole
+                        .chain((first..last).map(|group| captures.get(group)))
+                        .collect(),
+                )
+            }
+            Matcher::Word(matcher) => {
+                let (start, _, _) = self.find_pattern_at(line, start)?;
+                matcher.captures_at(line, start)
+            }
+            Matcher::Literal { .. } | Matcher::Literals(_) => {
+                let (start, end, _) = self.find_pattern_at(line, start)?;
+                Some(vec![Some((start, end))])
+            }
+        }
+    }
+
+    /*
+      Find byte range of the first match, which starts at `start` or later,
+      together with index of the pattern, which is matched
+    */
+    pub fn find_pattern_at(&self, line: &str, start: usize) -> Option<(usize, usize, usize)> {
+        match self {
+            Matcher::Literal {
+                query,
+                case_sensitive: true,
+            } => line[start..]
+                .find(query.as_str())
+                .map(|index| (start + index, start + index + query.len(), 0)),
+            Matcher::Literal {
+                query,
+                case_sensitive: false,
+            } => fold::find_at(line, query, start).map(|(start, end)| (start, end, 0)),
+            Matcher::Regex(regex) => regex
+                .find_at(line, start)
+                .map(|(start, end)| (start, end, 0)),
+            Matcher::Literals(automaton) => automaton.find_at(line, start),
+            Matcher::Regexes { regex, groups } => {
+                let captures = regex.captures_at(line, start)?;
+                let (start, end) = captures.get(0)?;
+                let pattern = groups
+                    .iter()
+                    .position(|group| captures.get(*group).is_some())?;
+
+                Some((start, end, pattern))
+            }
+            Matcher::Word(matcher) => {
+                let bounds = word_bounds(line);
+                let mut position = start;
+
+                // Skip matches, which start or end inside of a word
+                while let Some((start, end, pattern)) = matcher.find_pattern_at(line, position) {
+                    if start < end && bounds.contains(&start) && bounds.contains(&end) {
+                        return Some((start, end, pattern));
+                    }
+
+                    match line[start..].chars().next() {
+                        Some(c) => position = start + c.len_utf8(),
+                        None => break,
+                    }
+                }
+
+                None
+            }
+        }
+    }
+}
+
+/*
+  Byte offsets of Unicode word boundaries in the line,
+  they never split grapheme clusters, so `e` isn't a word in `e\u{301}`
+*/
+fn word_bounds(line: &str) -> Vec<usize> {
+    line.split_word_bound_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(line.len()))
         .collect()
 }
 
+/*
+  Help message, printed with `--help` flag
+*/
+pub const HELP: &str = "\
+Usage: minigrep [OPTIONS] QUERY PATH...
+       minigrep [OPTIONS] -e QUERY... PATH...
+       minigrep index build DIR
+
+Search for QUERY in the files, or in every file inside of directory PATH.
+When PATH is `-`, standard input is searched.
+`index build` writes trigram index into DIR, so later searches of DIR
+read only files, which could have QUERY (changed files are always read),
+put `--` before `index` to search for it instead.
+
+Options:
+  -i, --ignore-case   Ignore case distinctions
+  -E, --regex         Treat QUERY as a regular expression
+  -e, --regexp QUERY  Search for QUERY, could be repeated for several queries
+  -f, --file FILE     Take queries from FILE, one per line
+  -w, --word-regexp   Match only whole words
+  -v, --invert-match  Print lines, which don't match
+  -n, --line-number   Print line number before each line
+  -c, --count         Print only a count of matching lines
+  -b, --byte-offset   Print byte offset of the match before each line
+      --column        Print column of the match before each line
+  -A, --after-context NUM   Print NUM lines after each match
+  -B, --before-context NUM  Print NUM lines before each match
+  -C, --context NUM   Print NUM lines before and after each match
+  -j, --threads NUM   Search NUM files at once (default: amount of CPUs)
+      --output FORMAT Print results as `text` (default) or `json` lines
+      --color WHEN    Color matches `auto` (default), `always` or `never`
+      --replace TEXT  Print diff with matches replaced by TEXT,
+                      `$1` or `${1}` is a group of regular expression
+      --in-place      Write replaced text into the files
+      --fuzzy         Rank lines by fuzzy match of QUERY (its characters in order)
+      --top NUM       Print NUM best lines in fuzzy mode (default: 10)
+      --watch         Keep running, and print matching lines added to the files
+      --no-config     Don't read defaults from `.minigreprc`
+      --help          Print this help and exit
+  -V, --version       Print version and exit
+      --              Treat all following arguments as values
+
+Environment:
+  CASE_INSENSITIVE    Ignore case distinctions, when it is set
+
+Configuration:
+  Defaults are read from `.minigreprc` in the current directory or the closest
+  of its parents, one `flag = value` per line, e.g. `context = 2`,
+  `ignore-case = true` or `color = \"never\"`, command line flags win";
+
+// Short flags and long flags, which they stand for
+const SHORT_FLAGS: [(char, &str); 14] = [
+    ('i', "ignore-case"),
+    ('E', "regex"),
+    ('e', "regexp"),
+    ('f', "file"),
+    ('w', "word-regexp"),
+    ('v', "invert-match"),
+    ('n', "line-number"),
+    ('c', "count"),
+    ('b', "byte-offset"),
+    ('A', "after-context"),
+    ('B', "before-context"),
+    ('C', "context"),
+    ('j', "threads"),
+    ('V', "version"),
+];
+
+// Long flags, which need a value
+const VALUE_FLAGS: [&str; 10] = [
+    "regexp",
+    "file",
+    "after-context",
+    "before-context",
+    "context",
+    "threads",
+    "output",
+    "color",
+    "replace",
+    "top",
+];
+
+// Long flags, which could be set in `.minigreprc`
+const FILE_FLAGS: [&str; 15] = [
+    "ignore-case",
+    "regex",
+    "word-regexp",
+    "invert-match",
+    "line-number",
+    "count",
+    "byte-offset",
+    "column",
+    "after-context",
+    "before-context",
+    "context",
+    "threads",
+    "output",
+    "color",
+    "top",
+];
+
+/*
+  Errors, which could happen during parsing of arguments,
+  `Help` and `Version` are returned when user asked for them
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    MissingQuery,
+    MissingFilename,
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue(String, String),
+    UnexpectedValue(String),
+    InvalidRegex(RegexError),
+    UnreadablePatterns(String, String),
+    NeedsFlag(String, String),
+    Conflict(String, String),
+    MissingDirectory,
+    UnexpectedArgument(String),
+    InvalidConfigFile(String, String),
+    Help,
+    Version,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::MissingQuery => write!(f, "Didn't get a query string!"),
+            ConfigError::MissingFilename => write!(f, "Didn't get a file name!"),
+            ConfigError::UnknownFlag(flag) => write!(f, "Unknown flag `{}`", flag),
+            ConfigError::MissingValue(flag) => write!(f, "Flag `{}` needs a value", flag),
+            ConfigError::InvalidValue(flag, value) => {
+                write!(f, "Invalid value `{}` for flag `{}`", value, flag)
+            }
+            ConfigError::UnexpectedValue(flag) => write!(f, "Flag `{}` doesn't take a value", flag),
+            ConfigError::InvalidRegex(error) => write!(f, "Invalid regular expression: {}", error),
+            ConfigError::UnreadablePatterns(path, error) => {
+                write!(f, "Can't read queries from `{}`: {}", path, error)
+            }
+            ConfigError::InvalidConfigFile(path, error) => {
+                write!(f, "Invalid configuration file `{}`: {}", path, error)
+            }
+            ConfigError::NeedsFlag(flag, needed) => {
+                write!(f, "Flag `{}` works only with `{}`", flag, needed)
+            }
+            ConfigError::Conflict(flag, other) => {
+                write!(f, "Flags `{}` and `{}` can't be used together", flag, other)
+            }
+            ConfigError::MissingDirectory => write!(f, "Didn't get a directory to index!"),
+            ConfigError::UnexpectedArgument(arg) => write!(f, "Unexpected argument `{}`", arg),
+            ConfigError::Help => write!(f, "{}", HELP),
+            ConfigError::Version => write!(f, "minigrep {}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/*
+  What user asked for: search (the usual mode)
+  or building of trigram index with `index build DIR`
+*/
+#[derive(Debug)]
+pub enum Command {
+    Search(Config),
+    BuildIndex(PathBuf),
+}
+
+impl Command {
+    /*
+      Subcommand is recognised only by the first two arguments,
+      so `minigrep -- index build DIR` is a search for `index`
+    */
+    pub fn new(args: impl Iterator<Item = String>) -> Result<Command, ConfigError> {
+        let args: Vec<String> = args.collect();
+
+        match Command::index_build(&args)? {
+            Some(dir) => Ok(Command::BuildIndex(dir)),
+            None => Config::new(args.into_iter()).map(Command::Search),
+        }
+    }
+
+    // Directory of `index build DIR`, nothing is expected after it
+    fn index_build(args: &[String]) -> Result<Option<PathBuf>, ConfigError> {
+        match args {
+            [_, command, action, rest @ ..] if command == "index" && action == "build" => {
+                match rest {
+                    [] => Err(ConfigError::MissingDirectory),
+                    [dir] => Ok(Some(PathBuf::from(dir))),
+                    [_, extra, ..] => Err(ConfigError::UnexpectedArgument(extra.clone())),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
 /*
   Struct to save information about
   parsed arguments needed for the application,
-  in our case (query and filename)
+  in our case (queries, filenames and flags)
 */
+#[derive(Debug)]
 pub struct Config {
-    pub query: String,
-    pub filename: String,
+    pub patterns: Vec<String>,
+    pub filenames: Vec<String>,
     pub case_sensitive: bool,
+    pub matcher: Matcher,
+    pub line_number: bool,
+    pub count: bool,
+    pub invert: bool,
+    pub column: bool,
+    pub byte_offset: bool,
+    pub before_context: usize,
+    pub after_context: usize,
+    pub jobs: usize,
+    pub output: OutputFormat,
+    pub color: ColorChoice,
+    pub replace: Option<Replacement>,
+    pub in_place: bool,
+    pub fuzzy: bool,
+    pub top: usize,
+    pub watch: bool,
 }
 
 /*
@@ -68,35 +980,307 @@ pub struct Config {
 */
 impl Config {
     /*
-        Function to parse our cli arguments config,
-        and return tuple with query and filename.
+        Function to parse our cli arguments config:
+        flags could be placed anywhere, first value is a query
+        and all others are filenames, unless queries are given
+        with `-e` or `-f` flags, then all values are filenames.
+        Defaults are taken from `.minigreprc`, unless `--no-config` is given
     */
-    pub fn new(mut args: env::Args) -> Result<Config, &'static str> {
-        args.next(); // Will skip first cmd line argument, which is a path to a binary
+    pub fn new(args: impl Iterator<Item = String>) -> Result<Config, ConfigError> {
+        let args: Vec<String> = args.collect();
+        let no_config = Config::skips_defaults(&args);
 
-        // Get argument variable for a query
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string!"),
+        let file = env::current_dir().ok().and_then(|dir| rc::find(&dir));
+        let defaults = match file {
+            Some(path) if !no_config => Config::load_defaults(&path)?,
+            _ => Vec::new(),
         };
 
-        // Get argument variable for a filename
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string!"),
-        };
+        Config::with_defaults(defaults, args.into_iter())
+    }
+
+    /*
+      Check, if `--no-config` is given as a flag, parsing arguments
+      the same way as `with_defaults` does, so it isn't found in values
+      (`-e --no-config` or after `--`), invalid arguments are reported later
+    */
+    fn skips_defaults(args: &[String]) -> bool {
+        let mut parser = ArgParser::new(args.iter().skip(1).cloned());
+
+        while let Some(arg) = parser.next() {
+            if let Arg::Value(_) = arg {
+                continue;
+            }
+
+            match take_flag(arg, &mut parser) {
+                Ok((flag, None)) if flag == "no-config" => return true,
+                Ok(_) => {}
+                Err(_) => return false,
+            }
+        }
+
+        false
+    }
+
+    /*
+      Read `.minigreprc` and turn it into flags,
+      which are parsed before command line ones
+    */
+    fn load_defaults(path: &Path) -> Result<Vec<String>, ConfigError> {
+        let error =
+            |message: String| ConfigError::InvalidConfigFile(path.display().to_string(), message);
+
+        let contents = fs::read_to_string(path).map_err(|io_error| error(io_error.to_string()))?;
+        let entries = rc::parse(&contents).map_err(|rc_error| error(rc_error.to_string()))?;
+        let mut defaults = Vec::new();
+
+        for entry in entries {
+            if !FILE_FLAGS.contains(&entry.key.as_str()) {
+                let message = format!("unknown flag `{}`", entry.key);
+                return Err(error(rc::RcError::new(entry.line, &message).to_string()));
+            }
+
+            // Flags without value are turned on with `true`
+            match (VALUE_FLAGS.contains(&entry.key.as_str()), &entry.value) {
+                (false, rc::Value::Bool(true)) => defaults.push(format!("--{}", entry.key)),
+                (false, rc::Value::Bool(false)) => {}
+                (true, rc::Value::Integer(_) | rc::Value::String(_)) => {
+                    defaults.push(format!("--{}={}", entry.key, entry.value))
+                }
+                _ => {
+                    let message = format!("wrong type of value for `{}`", entry.key);
+                    return Err(error(rc::RcError::new(entry.line, &message).to_string()));
+                }
+            }
+        }
+
+        Ok(defaults)
+    }
+
+    /*
+      Parse arguments after `defaults` flags,
+      so flags from command line override them
+    */
+    pub fn with_defaults(
+        defaults: Vec<String>,
+        mut args: impl Iterator<Item = String>,
+    ) -> Result<Config, ConfigError> {
+        args.next(); // Will skip first cmd line argument, which is a path to a binary
+        let args = defaults.into_iter().chain(args);
 
         // Get environment variable to check if we are using case insensitive
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
+        let mut case_sensitive = env::var("CASE_INSENSITIVE").is_err();
+        let mut regex = false;
+        let mut word = false;
+        let mut line_number = false;
+        let mut count = false;
+        let mut invert = false;
+        let mut column = false;
+        let mut byte_offset = false;
+        let mut before_context = 0;
+        let mut after_context = 0;
+        let mut jobs = thread::available_parallelism().map_or(1, |jobs| jobs.get());
+        let mut output = OutputFormat::Text;
+        let mut color = ColorChoice::Auto;
+        let mut patterns: Option<Vec<String>> = None;
+        let mut replace = None;
+        let mut in_place = false;
+        let mut fuzzy = false;
+        let mut top = 10;
+        let mut watch = false;
+        let mut values = Vec::new();
+
+        let mut parser = ArgParser::new(args);
+        while let Some(arg) = parser.next() {
+            if let Arg::Value(value) = arg {
+                values.push(value);
+                continue;
+            }
+
+            // Flags, which need a value
+            let (flag, value) = take_flag(arg, &mut parser)?;
+
+            if let Some(value) = value {
+                let number = value.parse::<usize>().ok();
+
+                match (flag.as_str(), number) {
+                    ("regexp", _) => patterns.get_or_insert_with(Vec::new).push(value),
+                    ("file", _) => {
+                        let contents = fs::read_to_string(&value).map_err(|error| {
+                            ConfigError::UnreadablePatterns(value.clone(), error.to_string())
+                        })?;
+                        let file_patterns = contents.lines().map(|line| line.to_string());
+
+                        patterns.get_or_insert_with(Vec::new).extend(file_patterns);
+                    }
+                    ("replace", _) => replace = Some(value),
+                    ("output", _) if value == "text" => output = OutputFormat::Text,
+                    ("output", _) if value == "json" => output = OutputFormat::Json,
+                    ("color", _) if value == "auto" => color = ColorChoice::Auto,
+                    ("color", _) if value == "always" => color = ColorChoice::Always,
+                    ("color", _) if value == "never" => color = ColorChoice::Never,
+                    ("after-context", Some(number)) => after_context = number,
+                    ("before-context", Some(number)) => before_context = number,
+                    ("context", Some(number)) => {
+                        before_context = number;
+                        after_context = number;
+                    }
+                    ("threads", Some(number)) if number > 0 => jobs = number,
+                    ("top", Some(number)) if number > 0 => top = number,
+                    _ => return Err(ConfigError::InvalidValue(format!("--{}", flag), value)),
+                }
+                continue;
+            }
+
+            match flag.as_str() {
+                "ignore-case" => case_sensitive = false,
+                "regex" => regex = true,
+                "word-regexp" => word = true,
+                "invert-match" => invert = true,
+                "line-number" => line_number = true,
+                "count" => count = true,
+                "column" => column = true,
+                "byte-offset" => byte_offset = true,
+                "in-place" => in_place = true,
+                "fuzzy" => fuzzy = true,
+                "watch" => watch = true,
+                "no-config" => {}
+                "help" => return Err(ConfigError::Help),
+                "version" => return Err(ConfigError::Version),
+                _ => return Err(ConfigError::UnknownFlag(format!("--{}", flag))),
+            }
+        }
+
+        let mut values = values.into_iter();
+
+        // Get argument variable for a query, unless queries are given with flags
+        let patterns = match patterns {
+            Some(patterns) => patterns,
+            None => match values.next() {
+                Some(arg) => vec![arg],
+                None => return Err(ConfigError::MissingQuery),
+            },
+        };
+
+        // Get argument variables for filenames, at least one is needed
+        let filenames: Vec<String> = values.collect();
+        if filenames.is_empty() {
+            return Err(ConfigError::MissingFilename);
+        }
+
+        // Fuzzy mode has its own way to match and to print lines, which can't be mixed with others
+        let fuzzy_conflicts = [
+            (regex, "--regex"),
+            (replace.is_some(), "--replace"),
+            (invert, "--invert-match"),
+            (count, "--count"),
+            (word, "--word-regexp"),
+            (before_context > 0 || after_context > 0, "--context"),
+        ];
+        if let Some((_, other)) = fuzzy_conflicts.iter().find(|(set, _)| fuzzy && *set) {
+            return Err(ConfigError::Conflict(
+                String::from("--fuzzy"),
+                String::from(*other),
+            ));
+        }
+
+        // Watch mode prints only matched lines as they come
+        let watch_conflicts = [
+            (fuzzy, "--fuzzy"),
+            (replace.is_some(), "--replace"),
+            (count, "--count"),
+            (before_context > 0 || after_context > 0, "--context"),
+        ];
+        if let Some((_, other)) = watch_conflicts.iter().find(|(set, _)| watch && *set) {
+            return Err(ConfigError::Conflict(
+                String::from("--watch"),
+                String::from(*other),
+            ));
+        }
+
+        // Replace mode prints a diff, so other kinds of output can't be asked for
+        if replace.is_some() && (invert || count || output == OutputFormat::Json) {
+            let other = if invert {
+                "--invert-match"
+            } else if count {
+                "--count"
+            } else {
+                "--output=json"
+            };
+            return Err(ConfigError::Conflict(
+                String::from("--replace"),
+                String::from(other),
+            ));
+        }
+
+        // Compile regular expression once, so it could be reused for every line
+        let matcher = Matcher::with_patterns(&patterns, regex, word, case_sensitive)
+            .map_err(ConfigError::InvalidRegex)?;
+
+        // Group references are known only for regular expressions
+        let replace = replace.map(|text| Replacement::new(&text, regex));
+        if in_place && replace.is_none() {
+            return Err(ConfigError::NeedsFlag(
+                String::from("--in-place"),
+                String::from("--replace"),
+            ));
+        }
 
         Ok(Config {
-            query,
-            filename,
+            patterns,
+            filenames,
             case_sensitive,
+            matcher,
+            line_number,
+            count,
+            invert,
+            column,
+            byte_offset,
+            before_context,
+            after_context,
+            jobs,
+            output,
+            color,
+            replace,
+            in_place,
+            fuzzy,
+            top,
+            watch,
         })
     }
 }
 
+/*
+  Turn short flag into the long one, so both are handled in one place,
+  and take the value of the flag, which needs it (only such flags have a value)
+*/
+fn take_flag<I: Iterator<Item = String>>(
+    arg: Arg,
+    parser: &mut ArgParser<I>,
+) -> Result<(String, Option<String>), ConfigError> {
+    let (flag, inline_value) = match arg {
+        Arg::Short(c) => match SHORT_FLAGS.iter().find(|(short, _)| *short == c) {
+            Some((_, long)) => (long.to_string(), None),
+            None => return Err(ConfigError::UnknownFlag(format!("-{}", c))),
+        },
+        Arg::Long(name, value) => (name, value),
+        Arg::Value(_) => unreachable!("values are taken by the caller"),
+    };
+
+    if VALUE_FLAGS.contains(&flag.as_str()) {
+        return match inline_value.or_else(|| parser.value()) {
+            Some(value) => Ok((flag, Some(value))),
+            None => Err(ConfigError::MissingValue(format!("--{}", flag))),
+        };
+    }
+
+    if inline_value.is_some() {
+        return Err(ConfigError::UnexpectedValue(format!("--{}", flag)));
+    }
+
+    Ok((flag, None))
+}
+
 /*
   Define a test modules, to check if
   some functionality are worked
@@ -130,4 +1314,413 @@ Trust me.";
             search_case_insensitive(query, contents)
         );
     }
+
+    #[test]
+    fn fuzzy_results() {
+        let contents = "\
+fn search_case_insensitive() {}
+fn search(query: &str) {}
+let scratch_index = 1;
+fn print_results() {}";
+
+        // Consecutive `sc` beats two word starts
+        assert_eq!(
+            vec!["let scratch_index = 1;", "fn search_case_insensitive() {}"],
+            search_fuzzy("sci", contents, 2)
+        );
+        assert!(search_fuzzy("SCI", contents, 2).is_empty());
+    }
+
+    #[test]
+    fn regex_results() {
+        let regex = Regex::new(r"^fn \w+", true).unwrap();
+        let contents = "\
+fn main() {
+    let x = 5;
+}
+fn add(a: i32) {}
+  fn nested() {}";
+
+        assert_eq!(
+            vec!["fn main() {", "fn add(a: i32) {}"],
+            search_regex(&regex, contents)
+        );
+    }
+
+    #[test]
+    fn search_with_matcher() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Trust me.";
+        let literal = Matcher::Literal {
+            query: String::from("rust"),
+            case_sensitive: false,
+        };
+        let regex = Matcher::Regex(Regex::new("fast|me", true).unwrap());
+
+        assert_eq!(vec!["Rust:", "Trust me."], search_with(&literal, contents));
+        assert_eq!(
+            vec!["safe, fast, productive.", "Trust me."],
+            search_with(&regex, contents)
+        );
+    }
+
+    // Defaults from `.minigreprc` aren't loaded, so results don't depend on where tests run
+    fn config(args: &[&str]) -> Result<Config, ConfigError> {
+        Config::with_defaults(Vec::new(), args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn config_flags() {
+        let config = config(&["minigrep", "-nvc", "--regex", "a|b", "poem.txt"]).unwrap();
+
+        assert_eq!(vec!["a|b"], config.patterns);
+        assert_eq!(vec!["poem.txt"], config.filenames);
+        assert!(config.line_number && config.invert && config.count);
+        assert!(matches!(config.matcher, Matcher::Regex(_)));
+    }
+
+    #[test]
+    fn config_errors() {
+        assert_eq!(
+            ConfigError::MissingQuery,
+            config(&["minigrep"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::MissingFilename,
+            config(&["minigrep", "query"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::UnknownFlag(String::from("-x")),
+            config(&["minigrep", "-ix", "query", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::UnknownFlag(String::from("--colour")),
+            config(&["minigrep", "--colour", "query", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::InvalidValue(String::from("--threads"), String::from("0")),
+            config(&["minigrep", "-j0", "query", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::Help,
+            config(&["minigrep", "--help"]).unwrap_err()
+        );
+        assert!(matches!(
+            config(&["minigrep", "-E", "(a", "poem.txt"]).unwrap_err(),
+            ConfigError::InvalidRegex(_)
+        ));
+        assert_eq!(
+            ConfigError::Conflict(String::from("--fuzzy"), String::from("--regex")),
+            config(&["minigrep", "--fuzzy", "-E", "a", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::Conflict(String::from("--watch"), String::from("--replace")),
+            config(&["minigrep", "--watch", "--replace=b", "a", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::Conflict(String::from("--watch"), String::from("--count")),
+            config(&["minigrep", "--watch", "-c", "a", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::Conflict(String::from("--watch"), String::from("--context")),
+            config(&["minigrep", "--watch", "-A", "2", "a", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::Conflict(String::from("--replace"), String::from("--count")),
+            config(&["minigrep", "-c", "--replace=b", "a", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::Conflict(String::from("--replace"), String::from("--output=json")),
+            config(&[
+                "minigrep",
+                "--output",
+                "json",
+                "--replace=b",
+                "a",
+                "poem.txt"
+            ])
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn fuzzy_conflicts() {
+        for (flag, other) in [
+            ("-v", "--invert-match"),
+            ("-c", "--count"),
+            ("-w", "--word-regexp"),
+            ("-A1", "--context"),
+            ("-B1", "--context"),
+            ("-C1", "--context"),
+        ] {
+            assert_eq!(
+                ConfigError::Conflict(String::from("--fuzzy"), String::from(other)),
+                config(&["minigrep", "--fuzzy", flag, "a", "poem.txt"]).unwrap_err()
+            );
+        }
+        assert!(config(&["minigrep", "--fuzzy", "-C0", "a", "poem.txt"]).is_ok());
+    }
+
+    #[test]
+    fn index_build_command() {
+        let index_build = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            Command::index_build(&args)
+        };
+
+        assert_eq!(
+            Ok(Some(PathBuf::from("src"))),
+            index_build(&["minigrep", "index", "build", "src"])
+        );
+        assert_eq!(
+            Err(ConfigError::MissingDirectory),
+            index_build(&["minigrep", "index", "build"])
+        );
+        assert_eq!(
+            Err(ConfigError::UnexpectedArgument(String::from("-c"))),
+            index_build(&["minigrep", "index", "build", "src", "-c"])
+        );
+
+        // Only the first two arguments are the subcommand, `--` turns it into a search
+        assert_eq!(
+            Ok(None),
+            index_build(&["minigrep", "-n", "index", "build", "src"])
+        );
+        assert_eq!(
+            Ok(None),
+            index_build(&["minigrep", "--", "index", "build", "src"])
+        );
+        let config = config(&["minigrep", "--", "index", "build", "src"]).unwrap();
+        assert_eq!(vec!["index"], config.patterns);
+        assert_eq!(vec!["build", "src"], config.filenames);
+    }
+
+    #[test]
+    fn no_config_flag() {
+        let skips = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            Config::skips_defaults(&args)
+        };
+
+        assert!(skips(&["minigrep", "-n", "--no-config", "a", "poem.txt"]));
+        assert!(!skips(&["minigrep", "-e", "--no-config", "poem.txt"])); // Query of `-e`
+        assert!(!skips(&["minigrep", "-A", "--no-config", "a", "poem.txt"]));
+        assert!(!skips(&["minigrep", "a", "--", "--no-config"])); // File name
+    }
+
+    #[test]
+    fn defaults_from_config_file() {
+        let defaults = vec![String::from("--context=2"), String::from("--ignore-case")];
+        let args = ["minigrep", "-C", "1", "query", "poem.txt"];
+        let config =
+            Config::with_defaults(defaults, args.iter().map(|arg| arg.to_string())).unwrap();
+
+        assert_eq!((1, 1), (config.before_context, config.after_context));
+        assert!(!config.case_sensitive);
+
+        let dir = env::temp_dir().join(format!("minigrep-defaults-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(rc::FILE_NAME);
+
+        fs::write(
+            &path,
+            "line-number = true\ncount = false\ncolor = \"never\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["--line-number", "--color=never"],
+            Config::load_defaults(&path).unwrap()
+        );
+
+        fs::write(&path, "context = true").unwrap();
+        assert!(matches!(
+            Config::load_defaults(&path).unwrap_err(),
+            ConfigError::InvalidConfigFile(_, _)
+        ));
+
+        fs::write(&path, "replace = \"x\"").unwrap();
+        let error = Config::load_defaults(&path).unwrap_err().to_string();
+        assert!(error.ends_with("line 1: unknown flag `replace`"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn several_filenames() {
+        let config = config(&["minigrep", "-j", "2", "query", "a.txt", "src", "-"]).unwrap();
+
+        assert_eq!(vec!["a.txt", "src", "-"], config.filenames);
+        assert_eq!(2, config.jobs);
+    }
+
+    #[test]
+    fn query_after_double_dash() {
+        let config = config(&["minigrep", "--", "-v", "poem.txt"]).unwrap();
+
+        assert_eq!(vec!["-v"], config.patterns);
+        assert!(!config.invert);
+    }
+
+    #[test]
+    fn whole_words_and_inverted() {
+        let contents = "\
+the cat sat
+concatenate
+Cat!";
+        let mut config = config(&["minigrep", "-wi", "cat", "poem.txt"]).unwrap();
+
+        let lines: Vec<&str> = search_matches(&config, contents)
+            .iter()
+            .map(|found| found.line)
+            .collect();
+        assert_eq!(vec!["the cat sat", "Cat!"], lines);
+
+        config.invert = true;
+        let lines: Vec<&str> = search_matches(&config, contents)
+            .iter()
+            .map(|found| found.line)
+            .collect();
+        assert_eq!(vec!["concatenate"], lines);
+    }
+
+    #[test]
+    fn match_positions() {
+        let contents = "Rust:\r\nsafe, fast, productive.\nTrüst me.";
+        let config = config(&["minigrep", "-i", "ST", "poem.txt"]).unwrap();
+
+        assert_eq!(
+            vec![
+                Match {
+                    line_number: 1,
+                    column: 3,
+                    byte_offset: 2,
+                    line: "Rust:",
+                    submatches: vec![(2, 4)],
+                    patterns: vec![0],
+                },
+                Match {
+                    line_number: 2,
+                    column: 9,
+                    byte_offset: 15,
+                    line: "safe, fast, productive.",
+                    submatches: vec![(8, 10)],
+                    patterns: vec![0],
+                },
+                Match {
+                    line_number: 3,
+                    column: 4,
+                    byte_offset: 35,
+                    line: "Trüst me.",
+                    submatches: vec![(4, 6)],
+                    patterns: vec![0],
+                },
+            ],
+            search_matches(&config, contents)
+        );
+    }
+
+    #[test]
+    fn reader_with_invalid_utf8_and_context() {
+        let contents: &[u8] = b"one\ntwo \xff\nthree\nfour\nfive\nsix\n";
+        let config = config(&["minigrep", "-C1", "t", "-"]).unwrap();
+        let mut output = Vec::new();
+
+        let count = search_reader(&config, contents, |line| {
+            output.push(match line {
+                Line::Match(found) => format!("{}:{}", found.line_number, found.line),
+                Line::Context { line_number, line } => format!("{}-{}", line_number, line),
+                Line::Separator => String::from("--"),
+            });
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(2, count);
+        assert_eq!(vec!["1-one", "2:two \u{FFFD}", "3:three", "4-four"], output);
+    }
+
+    #[test]
+    fn unicode_case_and_words() {
+        let folded = Matcher::new("STRAẞE", false, false, false).unwrap();
+        let word = Matcher::new("кіт", false, true, false).unwrap();
+        let accented = Matcher::new("cafe", false, true, true).unwrap();
+
+        assert_eq!(vec![(0, 7)], folded.find_all("Straße, not STRASSE"));
+        assert_eq!(vec![(0, 6), (18, 24)], word.find_all("Кіт котик кіт"));
+        assert_eq!(vec![(7, 11)], accented.find_all("cafe\u{301} cafe"));
+    }
+
+    #[test]
+    fn several_patterns() {
+        let patterns = vec![String::from("fast"), String::from("rust")];
+        let literals = Matcher::with_patterns(&patterns, false, false, false).unwrap();
+        let patterns = vec![String::from("f(a)st"), String::from("(r)ust")];
+        let regexes = Matcher::with_patterns(&patterns, true, false, true).unwrap();
+
+        let line = "Rust: safe, fast, trusted";
+        assert_eq!(
+            vec![(0, 4, 1), (12, 16, 0), (19, 23, 1)],
+            literals.find_all_patterns(line)
+        );
+        assert_eq!(
+            vec![(12, 16, 0), (19, 23, 1)],
+            regexes.find_all_patterns(line)
+        );
+    }
+
+    #[test]
+    fn patterns_from_flags() {
+        let path = env::temp_dir().join(format!("minigrep-patterns-{}", std::process::id()));
+        fs::write(&path, "one\ntwo\n").unwrap();
+        let file = path.display().to_string();
+
+        let parsed = config(&["minigrep", "-e", "zero", "-f", &file, "poem.txt", "a.txt"]).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(vec!["zero", "one", "two"], parsed.patterns);
+        assert_eq!(vec!["poem.txt", "a.txt"], parsed.filenames);
+        assert!(matches!(parsed.matcher, Matcher::Literals(_)));
+        assert!(matches!(
+            config(&["minigrep", "-f", "missing.txt", "poem.txt"]).unwrap_err(),
+            ConfigError::UnreadablePatterns(_, _)
+        ));
+    }
+
+    #[test]
+    fn find_all_submatches() {
+        let literal = Matcher::new("ab", false, false, false).unwrap();
+        let regex = Matcher::new("x*", true, false, true).unwrap();
+
+        assert_eq!(vec![(0, 2), (3, 5)], literal.find_all("AB aB b"));
+        assert_eq!(vec![(1, 3)], regex.find_all("axxb"));
+        assert_eq!(vec![(0, 0)], regex.find_all("ab"));
+        assert!(Matcher::new("q", false, false, true)
+            .unwrap()
+            .find_all("ab")
+            .is_empty());
+    }
+
+    #[test]
+    fn context_flags() {
+        let parsed = config(&["minigrep", "-nC1", "-A", "2", "query", "poem.txt"]).unwrap();
+        assert_eq!((1, 2), (parsed.before_context, parsed.after_context));
+
+        let parsed = config(&["minigrep", "--context=3", "query", "poem.txt"]).unwrap();
+        assert_eq!((3, 3), (parsed.before_context, parsed.after_context));
+
+        assert_eq!(
+            ConfigError::MissingValue(String::from("--after-context")),
+            config(&["minigrep", "query", "poem.txt", "-A"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::InvalidValue(String::from("--context"), String::from("x")),
+            config(&["minigrep", "-Cx", "query", "poem.txt"]).unwrap_err()
+        );
+        assert_eq!(
+            ConfigError::UnexpectedValue(String::from("--count")),
+            config(&["minigrep", "--count=2", "query", "poem.txt"]).unwrap_err()
+        );
+    }
 }
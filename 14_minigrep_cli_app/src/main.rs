@@ -1,23 +1,35 @@
 use std::env;
+use std::path::Path;
 use std::process;
 
-use minigrep_cli_app::Config;
+use minigrep_cli_app::index::{self, Index};
+use minigrep_cli_app::{Command, ConfigError};
 
 fn main() {
     // Collect arguments from the cli, passed after cargo run command,
     // or executable, e.g: (cargo run test poem.txt) or (./app.exe test poem.exe)
-    //let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().collect();
+
+    // Get query and filename (or directory to index) from cli arguments
+    let command = Command::new(args.into_iter()).unwrap_or_else(|error| {
+        // Help and version are asked by user, so they aren't errors
+        if let ConfigError::Help | ConfigError::Version = error {
+            println!("{}", error);
+            process::exit(0);
+        }
 
-    // Get query and filename from cli arguments
-    let config: Config = Config::new(env::args()).unwrap_or_else(|error| {
         // Print error information
         eprintln!("Problem parsing arguments: {}", error);
+        eprintln!("Try `minigrep --help` for more information.");
         // Exit the application
         process::exit(1);
     });
 
-    println!("Searching for: `{}`", config.query);
-    println!("In file: `{}`", config.filename);
+    // `minigrep index build DIR` writes an index instead of searching
+    let config = match command {
+        Command::Search(config) => config,
+        Command::BuildIndex(dir) => return build_index(&dir),
+    };
 
     // Check for some errors in run function
     if let Err(error) = minigrep_cli_app::run(config) {
@@ -27,3 +39,20 @@ fn main() {
         process::exit(1);
     }
 }
+
+/*
+  Build trigram index of the directory and write it inside of it
+*/
+fn build_index(dir: &Path) {
+    match Index::build(dir).and_then(|index| index.write(dir).map(|_| index.len())) {
+        Ok(files) => println!(
+            "Indexed {} file(s) into `{}`",
+            files,
+            dir.join(index::FILE_NAME).display()
+        ),
+        Err(error) => {
+            eprintln!("Application error: {}", error);
+            process::exit(1);
+        }
+    }
+}
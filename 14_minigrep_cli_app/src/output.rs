@@ -0,0 +1,487 @@
+// Printing of search results, as plain text or as JSON Lines
+
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use colored::{ColoredString, Colorize};
+
+use crate::context::Line;
+use crate::fuzzy::Ranked;
+use crate::json::Json;
+use crate::{search_reader, Config, Match};
+
+/*
+  Format of the output, chosen with `--output` flag
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/*
+  When output should be colored, chosen with `--color` flag
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /*
+      Check if colors are turned on, `Auto` colors output only
+      for a terminal, and respects `NO_COLOR` environment variable
+    */
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        }
+    }
+}
+
+/*
+  Totals for all searched files,
+  printed as the last record of JSON output
+*/
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    pub files: usize,
+    pub files_with_matches: usize,
+    pub matches: usize,
+}
+
+impl Summary {
+    // Count one more searched file with its amount of matched lines
+    pub fn add(&mut self, matches: usize) {
+        self.files += 1;
+        self.matches += matches;
+
+        if matches > 0 {
+            self.files_with_matches += 1;
+        }
+    }
+}
+
+/*
+  Print results of a single file in format from config,
+  path is shown in text output only when `with_path` is set,
+  returns amount of matched lines
+*/
+pub fn print_results<R: BufRead, W: Write>(
+    config: &Config,
+    path: &Path,
+    with_path: bool,
+    reader: R,
+    out: &mut W,
+) -> io::Result<usize> {
+    match config.output {
+        OutputFormat::Text => {
+            let path = Some(path).filter(|_| with_path);
+            print_text(config, path, reader, out)
+        }
+        OutputFormat::Json => print_json(config, path, reader, out),
+    }
+}
+
+/*
+  Print all lines, that contains a provided query (or only their count),
+  with lines around them, prefixed with path when it is given
+*/
+fn print_text<R: BufRead, W: Write>(
+    config: &Config,
+    path: Option<&Path>,
+    reader: R,
+    out: &mut W,
+) -> io::Result<usize> {
+    let color = config.color.enabled();
+
+    let count = search_reader(config, reader, |line| match line {
+        Line::Separator => writeln!(out, "{}", paint("--", color, |text| text.cyan())),
+        Line::Context { line_number, line } => {
+            let numbers: Vec<usize> = Some(line_number)
+                .filter(|_| config.line_number)
+                .into_iter()
+                .collect();
+
+            let prefix = format_prefix(path, &numbers, '-', color);
+            writeln!(out, "{}{}", prefix, line)
+        }
+        Line::Match(found) => writeln!(out, "{}", match_text(config, path, &found, color)),
+    })?;
+
+    if config.count {
+        writeln!(out, "{}{}", format_prefix(path, &[], ':', color), count)?;
+    }
+
+    Ok(count)
+}
+
+// Matched line with prefix of its position, and highlighted matches
+fn match_text(config: &Config, path: Option<&Path>, found: &Match, color: bool) -> String {
+    let mut numbers = Vec::new();
+
+    // Column is useless without a line, so it turns line numbers on
+    if config.line_number || config.column {
+        numbers.push(found.line_number);
+    }
+    if config.column {
+        numbers.push(found.column);
+    }
+    if config.byte_offset {
+        numbers.push(found.byte_offset);
+    }
+
+    let prefix = format_prefix(path, &numbers, ':', color);
+    format!("{}{}", prefix, highlight(found, color))
+}
+
+/*
+  Print single matched line, which is found outside of file search
+  (e.g. in watch mode), the same way as file search prints it
+*/
+pub fn print_match<W: Write>(
+    config: &Config,
+    path: &Path,
+    with_path: bool,
+    found: &Match,
+    out: &mut W,
+) -> io::Result<()> {
+    match config.output {
+        OutputFormat::Text => {
+            let path = Some(path).filter(|_| with_path);
+            let color = config.color.enabled();
+            writeln!(out, "{}", match_text(config, path, found, color))
+        }
+        OutputFormat::Json => {
+            let path = path.display().to_string();
+            writeln!(out, "{}", match_record(&path, found, &config.patterns))
+        }
+    }
+}
+
+/*
+  Prefix of printed line, e.g. `src/main.rs:12:5:`,
+  matched lines are separated with `:` and context lines with `-`
+*/
+fn format_prefix(path: Option<&Path>, numbers: &[usize], separator: char, color: bool) -> String {
+    let separator = paint(&separator.to_string(), color, |text| text.cyan());
+    let mut prefix = String::new();
+
+    if let Some(path) = path {
+        let path = path.display().to_string();
+        prefix.push_str(&paint(&path, color, |text| text.magenta()));
+        prefix.push_str(&separator);
+    }
+
+    for number in numbers {
+        prefix.push_str(&paint(&number.to_string(), color, |text| text.green()));
+        prefix.push_str(&separator);
+    }
+
+    prefix
+}
+
+/*
+  Matched line with every submatch painted,
+  spans are taken from the match record, nothing is searched again
+*/
+fn highlight(found: &Match, color: bool) -> String {
+    if !color {
+        return found.line.to_string();
+    }
+
+    let mut line = String::new();
+    let mut position = 0;
+
+    for (start, end) in &found.submatches {
+        line.push_str(&found.line[position..*start]);
+        line.push_str(&found.line[*start..*end].red().bold().to_string());
+        position = *end;
+    }
+    line.push_str(&found.line[position..]);
+
+    line
+}
+
+// Paint text with the style only when colors are turned on
+pub(crate) fn paint(text: &str, color: bool, style: impl Fn(&str) -> ColoredString) -> String {
+    if color {
+        style(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/*
+  Print one JSON object per line: `begin` before the first match of the file,
+  `match` and `context` for lines, and `end` with amount of matches,
+  files without matches print nothing
+*/
+fn print_json<R: BufRead, W: Write>(
+    config: &Config,
+    path: &Path,
+    reader: R,
+    out: &mut W,
+) -> io::Result<usize> {
+    let path = path.display().to_string();
+    let mut begun = false;
+
+    let count = search_reader(config, reader, |line| {
+        let record = match line {
+            // Groups are already clear from line numbers
+            Line::Separator => return Ok(()),
+            Line::Context { line_number, line } => Json::object()
+                .field("type", "context")
+                .field("path", path.as_str())
+                .field("line_number", line_number)
+                .field("line", line),
+            Line::Match(found) => match_record(&path, &found, &config.patterns),
+        };
+
+        if !begun {
+            writeln!(out, "{}", begin_record(&path))?;
+            begun = true;
+        }
+
+        writeln!(out, "{}", record)
+    })?;
+
+    // In count mode no lines are passed, so file could be not begun yet
+    if count > 0 && !begun {
+        writeln!(out, "{}", begin_record(&path))?;
+        begun = true;
+    }
+
+    if begun {
+        let end = Json::object()
+            .field("type", "end")
+            .field("path", path.as_str())
+            .field("matches", count);
+        writeln!(out, "{}", end)?;
+    }
+
+    Ok(count)
+}
+
+fn begin_record(path: &str) -> Json {
+    Json::object().field("type", "begin").field("path", path)
+}
+
+/*
+  Record for matched line, with text and position of every submatch
+  and the query, which found it
+*/
+fn match_record(path: &str, found: &Match, patterns: &[String]) -> Json {
+    let submatches: Vec<Json> = found
+        .submatches
+        .iter()
+        .zip(&found.patterns)
+        .map(|((start, end), pattern)| {
+            Json::object()
+                .field("match", &found.line[*start..*end])
+                .field("pattern", patterns[*pattern].as_str())
+                .field("start", *start)
+                .field("end", *end)
+                .field("column", found.line[..*start].chars().count() + 1)
+        })
+        .collect();
+
+    Json::object()
+        .field("type", "match")
+        .field("path", path)
+        .field("line_number", found.line_number)
+        .field("byte_offset", found.byte_offset)
+        .field("line", found.line)
+        .field("submatches", submatches)
+}
+
+/*
+  Print lines found in fuzzy mode, the best first,
+  matched characters are highlighted, JSON records have the score
+*/
+pub fn print_ranked<W: Write>(
+    config: &Config,
+    lines: &[Ranked],
+    with_path: bool,
+    out: &mut W,
+) -> io::Result<()> {
+    let color = config.color.enabled();
+
+    for ranked in lines {
+        if config.output == OutputFormat::Json {
+            let positions: Vec<Json> = ranked.positions.iter().map(|p| Json::from(*p)).collect();
+            let record = Json::object()
+                .field("type", "fuzzy")
+                .field("path", ranked.path.display().to_string())
+                .field("line_number", ranked.line_number)
+                .field("score", ranked.score)
+                .field("line", ranked.line.as_str())
+                .field("positions", positions);
+
+            writeln!(out, "{}", record)?;
+            continue;
+        }
+
+        let path = Some(ranked.path.as_path()).filter(|_| with_path);
+        let numbers: Vec<usize> = Some(ranked.line_number)
+            .filter(|_| config.line_number)
+            .into_iter()
+            .collect();
+
+        let mut line = String::new();
+        for (index, c) in ranked.line.char_indices() {
+            if color && ranked.positions.contains(&index) {
+                line.push_str(&c.to_string().red().bold().to_string());
+            } else {
+                line.push(c);
+            }
+        }
+
+        writeln!(out, "{}{}", format_prefix(path, &numbers, ':', color), line)?;
+    }
+
+    Ok(())
+}
+
+/*
+  Print the last record of JSON output with totals for all files
+*/
+pub fn print_summary<W: Write>(summary: &Summary, out: &mut W) -> io::Result<()> {
+    let record = Json::object()
+        .field("type", "summary")
+        .field("files", summary.files)
+        .field("files_with_matches", summary.files_with_matches)
+        .field("matches", summary.matches);
+
+    writeln!(out, "{}", record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colored_text() {
+        let args = ["minigrep", "-nE", "--color=always", "b+", "a.txt", "b.txt"];
+        let config =
+            Config::with_defaults(Vec::new(), args.iter().map(|arg| arg.to_string())).unwrap();
+        colored::control::set_override(true);
+
+        let mut out = Vec::new();
+        print_results(&config, Path::new("a.txt"), true, &b"abba b"[..], &mut out).unwrap();
+
+        assert_eq!(
+            concat!(
+                "\u{1b}[35ma.txt\u{1b}[0m\u{1b}[36m:\u{1b}[0m\u{1b}[32m1\u{1b}[0m\u{1b}[36m:\u{1b}[0m",
+                "a\u{1b}[1;31mbb\u{1b}[0ma \u{1b}[1;31mb\u{1b}[0m\n"
+            ),
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn plain_text() {
+        let args = ["minigrep", "-nE", "--color", "never", "b+", "a.txt"];
+        let config =
+            Config::with_defaults(Vec::new(), args.iter().map(|arg| arg.to_string())).unwrap();
+
+        let mut out = Vec::new();
+        print_results(&config, Path::new("a.txt"), true, &b"abba b"[..], &mut out).unwrap();
+
+        assert_eq!("a.txt:1:abba b\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn json_records() {
+        let args = ["minigrep", "-E", "-A1", "b+", "file.txt"];
+        let mut config =
+            Config::with_defaults(Vec::new(), args.iter().map(|arg| arg.to_string())).unwrap();
+        config.output = OutputFormat::Json;
+
+        let contents: &[u8] = b"abba b\n\"next\"\nnone\n";
+        let mut out = Vec::new();
+
+        let count =
+            print_results(&config, Path::new("file.txt"), false, contents, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(1, count);
+        assert_eq!(
+            vec![
+                r#"{"type":"begin","path":"file.txt"}"#,
+                concat!(
+                    r#"{"type":"match","path":"file.txt","line_number":1,"byte_offset":1,"line":"abba b","#,
+                    r#""submatches":[{"match":"bb","pattern":"b+","start":1,"end":3,"column":2},"#,
+                    r#"{"match":"b","pattern":"b+","start":5,"end":6,"column":6}]}"#
+                ),
+                r#"{"type":"context","path":"file.txt","line_number":2,"line":"\"next\""}"#,
+                r#"{"type":"end","path":"file.txt","matches":1}"#,
+            ],
+            out.lines().collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn json_reports_matched_pattern() {
+        let args = [
+            "minigrep",
+            "--output=json",
+            "-e",
+            "cat",
+            "-e",
+            "dog",
+            "file.txt",
+        ];
+        let config =
+            Config::with_defaults(Vec::new(), args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut out = Vec::new();
+
+        print_results(
+            &config,
+            Path::new("file.txt"),
+            true,
+            &b"dog, cat"[..],
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains(r#"{"match":"dog","pattern":"dog","start":0,"end":3,"column":1}"#));
+        assert!(out.contains(r#"{"match":"cat","pattern":"cat","start":5,"end":8,"column":6}"#));
+    }
+
+    #[test]
+    fn json_skips_files_without_matches() {
+        let args = ["minigrep", "--output=json", "missing", "file.txt"];
+        let config =
+            Config::with_defaults(Vec::new(), args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut out = Vec::new();
+
+        let count =
+            print_results(&config, Path::new("file.txt"), true, &b"text"[..], &mut out).unwrap();
+
+        assert_eq!(0, count);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn summary_totals() {
+        let mut summary = Summary::default();
+        summary.add(3);
+        summary.add(0);
+        summary.add(2);
+
+        let mut out = Vec::new();
+        print_summary(&summary, &mut out).unwrap();
+
+        assert_eq!(
+            "{\"type\":\"summary\",\"files\":3,\"files_with_matches\":2,\"matches\":5}\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,249 @@
+// Pool of worker threads, which keeps results in the order of input
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+// Size of output chunk, which worker hands over at once
+const CHUNK_SIZE: usize = 64 * 1024;
+// Chunks of one item, which could wait for the consumer, later workers block after them
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+/*
+  Run `work` for every item on (at most) `threads` worker threads,
+  results are passed to `consume` on the current thread in the same
+  order as items, no matter which worker finished first
+*/
+pub fn for_each_ordered<T, R, W, C>(items: &[T], threads: usize, work: W, mut consume: C)
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    C: FnMut(&T, R),
+{
+    // Index of the next item, which is taken by a free worker
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, items.len().max(1)) {
+            let sender = sender.clone();
+            let next = &next;
+            let work = &work;
+
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= items.len() {
+                    break;
+                }
+
+                // Receiver is gone only when consumer panicked, so just stop
+                if sender.send((index, work(&items[index]))).is_err() {
+                    break;
+                }
+            });
+        }
+
+        // Only workers hold senders now, so channel is closed when they are done
+        drop(sender);
+
+        // Results, which came earlier than the ones before them
+        let mut pending = HashMap::new();
+        let mut expected = 0;
+
+        for (index, result) in receiver {
+            pending.insert(index, result);
+
+            while let Some(result) = pending.remove(&expected) {
+                consume(&items[expected], result);
+                expected += 1;
+            }
+        }
+    });
+}
+
+/*
+  Output of a single item, which is handed over by chunks,
+  `Done` is the last message with the result of the work
+*/
+pub enum Streamed<R> {
+    Chunk(Vec<u8>),
+    Done(R),
+}
+
+/*
+  Writer of the worker: output is cut into chunks, and every chunk is sent
+  over the bounded channel of the item, so only a few chunks are kept in memory
+*/
+pub struct ChunkWriter<R> {
+    buffer: Vec<u8>,
+    sender: SyncSender<Streamed<R>>,
+}
+
+impl<R> ChunkWriter<R> {
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+        self.sender
+            .send(Streamed::Chunk(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "output is closed"))
+    }
+}
+
+impl<R> Write for ChunkWriter<R> {
+    // Chunk never grows over its size, the rest of data is taken by the next call
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let taken = data.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..taken]);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(taken)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+/*
+  Run `work` for every item on (at most) `threads` worker threads, like `for_each_ordered`,
+  but output written by `work` is passed to `consume` by chunks as it comes, in order of items.
+  Only the first unfinished item is consumed, later workers wait when their channels are full,
+  so output of a big item never has to fit in memory
+*/
+pub fn for_each_streamed<T, R, W, C>(items: &[T], threads: usize, work: W, mut consume: C)
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T, &mut ChunkWriter<R>) -> R + Sync,
+    C: FnMut(&T, Streamed<R>),
+{
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel::<(usize, Receiver<Streamed<R>>)>();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, items.len().max(1)) {
+            let sender = sender.clone();
+            let next = &next;
+            let work = &work;
+
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= items.len() {
+                    break;
+                }
+
+                let (chunks, item_receiver) = mpsc::sync_channel(CHUNKS_IN_FLIGHT);
+                if sender.send((index, item_receiver)).is_err() {
+                    break;
+                }
+
+                let mut writer = ChunkWriter {
+                    buffer: Vec::with_capacity(CHUNK_SIZE),
+                    sender: chunks,
+                };
+                let result = work(&items[index], &mut writer);
+
+                // Receiver is gone only when consumer panicked, so just stop
+                if writer.send_buffer().is_err()
+                    || writer.sender.send(Streamed::Done(result)).is_err()
+                {
+                    break;
+                }
+            });
+        }
+
+        drop(sender);
+
+        // Channels of items, which started before the ones before them are done
+        let mut pending = HashMap::new();
+        let mut expected = 0;
+
+        for (index, item_receiver) in receiver {
+            pending.insert(index, item_receiver);
+
+            while let Some(item_receiver) = pending.remove(&expected) {
+                for message in item_receiver {
+                    consume(&items[expected], message);
+                }
+                expected += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn results_keep_order() {
+        let items: Vec<u64> = (0..20).collect();
+        let mut results = Vec::new();
+
+        // Earlier items are slower, so they finish last
+        for_each_ordered(
+            &items,
+            4,
+            |item| {
+                thread::sleep(Duration::from_millis(20 - item));
+                item * 2
+            },
+            |item, result| results.push((*item, result)),
+        );
+
+        let expected: Vec<(u64, u64)> = items.iter().map(|item| (*item, item * 2)).collect();
+        assert_eq!(expected, results);
+    }
+
+    #[test]
+    fn streamed_output_keeps_order() {
+        let items: Vec<usize> = (0..8).collect();
+        let mut output = Vec::new();
+        let mut results = Vec::new();
+
+        // Every item writes several chunks, earlier items are slower
+        for_each_streamed(
+            &items,
+            4,
+            |item, out| {
+                thread::sleep(Duration::from_millis(8 - *item as u64));
+                out.write_all(&vec![b'a' + *item as u8; CHUNK_SIZE * 3])
+                    .unwrap();
+                *item
+            },
+            |_, message| match message {
+                Streamed::Chunk(chunk) => {
+                    assert!(chunk.len() <= CHUNK_SIZE);
+                    output.extend(chunk);
+                }
+                Streamed::Done(result) => results.push(result),
+            },
+        );
+
+        let expected: Vec<u8> = items
+            .iter()
+            .flat_map(|item| vec![b'a' + *item as u8; CHUNK_SIZE * 3])
+            .collect();
+        assert!(output == expected);
+        assert_eq!(items, results);
+    }
+
+    #[test]
+    fn no_items() {
+        let items: Vec<u64> = Vec::new();
+        let mut called = false;
+
+        for_each_ordered(&items, 4, |item| *item, |_, _| called = true);
+
+        assert!(!called);
+    }
+}
@@ -0,0 +1,217 @@
+// Configuration file `.minigreprc` with default flags for a project
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = ".minigreprc";
+
+/*
+  Value of the key: `true`/`false`, integer
+  or string in double quotes (like in TOML)
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// Single `key = value` line of the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub line: usize,
+    pub key: String,
+    pub value: Value,
+}
+
+/*
+  Error in the file, keeps the line number where it was found
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct RcError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl RcError {
+    pub fn new(line: usize, message: &str) -> RcError {
+        RcError {
+            line,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/*
+  Find the file in the directory or in the closest of its parents,
+  so every project could have its own defaults
+*/
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/*
+  Parse contents of the file: one `key = value` per line,
+  empty lines and comments after `#` are skipped
+*/
+pub fn parse(contents: &str) -> Result<Vec<Entry>, RcError> {
+    let mut entries: Vec<Entry> = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(RcError::new(number, "sections aren't supported"));
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return Err(RcError::new(number, "expected `key = value`")),
+        };
+
+        let valid_key = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if key.is_empty() || !key.chars().all(valid_key) {
+            return Err(RcError::new(number, "invalid key"));
+        }
+        if entries.iter().any(|entry| entry.key == key) {
+            return Err(RcError::new(number, "duplicate key"));
+        }
+
+        entries.push(Entry {
+            line: number,
+            key: key.to_string(),
+            value: parse_value(value).map_err(|message| RcError::new(number, message))?,
+        });
+    }
+
+    Ok(entries)
+}
+
+// Parse value with optional comment after it
+fn parse_value(text: &str) -> Result<Value, &'static str> {
+    if let Some(quoted) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    let rest = quoted[index + 1..].trim_start();
+
+                    return if rest.is_empty() || rest.starts_with('#') {
+                        Ok(Value::String(value))
+                    } else {
+                        Err("unexpected text after the value")
+                    };
+                }
+                '\\' => match chars.next() {
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    _ => return Err("unknown escape sequence"),
+                },
+                c => value.push(c),
+            }
+        }
+
+        return Err("string isn't closed");
+    }
+
+    let value = text.split('#').next().unwrap_or("").trim();
+
+    match value {
+        "" => Err("missing value"),
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        value => value
+            .parse::<i64>()
+            .map(Value::Integer)
+            .map_err(|_| "strings must be in double quotes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn parse_entries() {
+        let contents = "\
+# Defaults for this project
+ignore-case = true
+context = 2 # lines around matches
+
+color = \"ne\\\"ver\" # quoted
+";
+        let entries = parse(contents).unwrap();
+
+        assert_eq!(
+            vec![
+                (2, "ignore-case", Value::Bool(true)),
+                (3, "context", Value::Integer(2)),
+                (5, "color", Value::String(String::from("ne\"ver"))),
+            ],
+            entries
+                .iter()
+                .map(|entry| (entry.line, entry.key.as_str(), entry.value.clone()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Err(RcError::new(2, "expected `key = value`")),
+            parse("a = 1\nb")
+        );
+        assert_eq!(
+            Err(RcError::new(1, "strings must be in double quotes")),
+            parse("color = never")
+        );
+        assert_eq!(
+            Err(RcError::new(1, "string isn't closed")),
+            parse("a = \"b")
+        );
+        assert_eq!(Err(RcError::new(2, "duplicate key")), parse("a = 1\na = 2"));
+        assert_eq!(
+            Err(RcError::new(1, "sections aren't supported")),
+            parse("[search]")
+        );
+    }
+
+    #[test]
+    fn find_in_parents() {
+        let root = env::temp_dir().join(format!("minigrep-rc-{}", std::process::id()));
+        let nested = root.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(FILE_NAME), "count = true").unwrap();
+
+        assert_eq!(Some(root.join(FILE_NAME)), find(&nested));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
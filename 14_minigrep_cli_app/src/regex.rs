@@ -0,0 +1,985 @@
+// Small regular expression engine, used by the `--regex` search mode
+
+use std::error::Error;
+use std::fmt;
+
+use crate::fold::{case_variants, fold};
+
+// Upper bound for compiled program, so patterns like `(a{1000}){1000}` can't eat all memory
+const MAX_PROGRAM_SIZE: usize = 100_000;
+
+// Upper bound for a single counted repetition, e.g. `a{1000}`
+const MAX_REPEAT: u32 = 1_000;
+
+/*
+  Error returned when pattern can't be compiled,
+  keeps the byte position in pattern where it was found
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegexError {
+    pub message: &'static str,
+    pub position: usize,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.position)
+    }
+}
+
+impl Error for RegexError {}
+
+/*
+  Single item inside of character class,
+  e.g. range `a-z` or shorthand `\w`
+*/
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Word(bool),
+    Digit(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(start, end) => start <= c && c <= end,
+            ClassItem::Word(negated) => is_word_char(c) != negated,
+            ClassItem::Digit(negated) => c.is_ascii_digit() != negated,
+            ClassItem::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+/*
+  Character class, e.g. `[a-z_]`, `[^0-9]` or `\d`
+*/
+#[derive(Debug, Clone, PartialEq)]
+struct CharClass {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+impl CharClass {
+    fn single(item: ClassItem) -> CharClass {
+        CharClass {
+            items: vec![item],
+            negated: false,
+        }
+    }
+
+    fn matches(&self, c: char, case_sensitive: bool) -> bool {
+        let found = if case_sensitive {
+            self.items.iter().any(|item| item.matches(c))
+        } else {
+            // Try every case variant of the character
+            case_variants(c)
+                .into_iter()
+                .any(|variant| self.items.iter().any(|item| item.matches(variant)))
+        };
+
+        found != self.negated
+    }
+}
+
+/*
+  Zero width assertions, which don't consume any character
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Assertion {
+    Start,
+    End,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+/*
+  Syntax tree of parsed pattern
+*/
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Empty,
+    Literal(char),
+    Any,
+    Class(CharClass),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+/*
+  Recursive descent parser, which turns pattern into the syntax tree
+*/
+struct Parser<'p> {
+    pattern: &'p str,
+    position: usize,
+    groups: usize,
+}
+
+impl<'p> Parser<'p> {
+    fn new(pattern: &'p str) -> Parser<'p> {
+        Parser {
+            pattern,
+            position: 0,
+            groups: 0,
+        }
+    }
+
+    fn error(&self, message: &'static str) -> RegexError {
+        RegexError {
+            message,
+            position: self.position,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.pattern[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse(mut self) -> Result<(Node, usize), RegexError> {
+        let node = self.parse_alternation()?;
+
+        // Whole pattern must be consumed, only stray `)` could stop us here
+        if self.peek().is_some() {
+            return Err(self.error("unmatched closing parenthesis"));
+        }
+
+        Ok((node, self.groups))
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, RegexError> {
+        let mut branches = vec![self.parse_concat()?];
+
+        while self.eat('|') {
+            branches.push(self.parse_concat()?);
+        }
+
+        if branches.len() == 1 {
+            Ok(branches.remove(0))
+        } else {
+            Ok(Node::Alternate(branches))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = Vec::new();
+
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_repeat(atom)?);
+        }
+
+        match nodes.len() {
+            0 => Ok(Node::Empty),
+            1 => Ok(nodes.remove(0)),
+            _ => Ok(Node::Concat(nodes)),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, RegexError> {
+        let start = self.position;
+
+        match self.next() {
+            Some('(') => {
+                // Non capturing group, e.g. `(?:ab)+`
+                let index = if self.pattern[self.position..].starts_with("?:") {
+                    self.position += 2;
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+
+                let inner = self.parse_alternation()?;
+
+                if !self.eat(')') {
+                    return Err(RegexError {
+                        message: "unclosed group",
+                        position: start,
+                    });
+                }
+
+                Ok(Node::Group(Box::new(inner), index))
+            }
+            Some('[') => self.parse_class(start),
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Assert(Assertion::Start)),
+            Some('$') => Ok(Node::Assert(Assertion::End)),
+            Some('\\') => self.parse_escape(),
+            Some('*') | Some('+') | Some('?') => {
+                self.position = start;
+                Err(self.error("repetition operator without operand"))
+            }
+            // `{` is a literal, unless it starts a repetition count, e.g. `fn main() {`
+            Some('{') => {
+                self.position = start;
+                if self.parse_counts()?.is_some() {
+                    self.position = start;
+                    return Err(self.error("repetition operator without operand"));
+                }
+                self.position = start + 1;
+                Ok(Node::Literal('{'))
+            }
+            Some(c) => Ok(Node::Literal(c)),
+            None => Err(self.error("unexpected end of pattern")),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, RegexError> {
+        let node = match self.next() {
+            Some('b') => Node::Assert(Assertion::WordBoundary),
+            Some('B') => Node::Assert(Assertion::NotWordBoundary),
+            Some(c) => match shorthand_class(c) {
+                Some(item) => Node::Class(CharClass::single(item)),
+                None => Node::Literal(self.escaped_char(c)?),
+            },
+            None => return Err(self.error("trailing backslash")),
+        };
+
+        Ok(node)
+    }
+
+    // Character which stands after backslash, when it isn't a class shorthand
+    fn escaped_char(&self, c: char) -> Result<char, RegexError> {
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            c if c.is_ascii_alphanumeric() => Err(self.error("unknown escape sequence")),
+            c => Ok(c),
+        }
+    }
+
+    fn parse_class(&mut self, start: usize) -> Result<Node, RegexError> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+
+        loop {
+            let c = match self.next() {
+                Some(c) => c,
+                None => {
+                    return Err(RegexError {
+                        message: "unclosed character class",
+                        position: start,
+                    })
+                }
+            };
+
+            // `]` right after `[` or `[^` is a literal character
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+
+            let low = if c == '\\' {
+                let escaped = match self.next() {
+                    Some(escaped) => escaped,
+                    None => return Err(self.error("trailing backslash")),
+                };
+
+                if let Some(item) = shorthand_class(escaped) {
+                    items.push(item);
+                    continue;
+                }
+
+                self.escaped_char(escaped)?
+            } else {
+                c
+            };
+
+            // Range, unless `-` is the last character of the class
+            let rest = &self.pattern[self.position..];
+            if rest.starts_with('-') && !rest.starts_with("-]") && rest.len() > 1 {
+                self.position += 1;
+
+                let high = match self.next() {
+                    Some('\\') => match self.next() {
+                        Some(escaped) => self.escaped_char(escaped)?,
+                        None => return Err(self.error("trailing backslash")),
+                    },
+                    Some(high) => high,
+                    None => return Err(self.error("unclosed character class")),
+                };
+
+                if high < low {
+                    return Err(self.error("invalid character class range"));
+                }
+
+                items.push(ClassItem::Range(low, high));
+            } else {
+                items.push(ClassItem::Range(low, low));
+            }
+        }
+
+        Ok(Node::Class(CharClass { items, negated }))
+    }
+
+    fn parse_repeat(&mut self, mut atom: Node) -> Result<Node, RegexError> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => {
+                    self.position += 1;
+                    (0, None)
+                }
+                Some('+') => {
+                    self.position += 1;
+                    (1, None)
+                }
+                Some('?') => {
+                    self.position += 1;
+                    (0, Some(1))
+                }
+                // `{` without a valid count after it is a literal of the next atom
+                Some('{') => match self.parse_counts()? {
+                    Some(counts) => counts,
+                    None => return Ok(atom),
+                },
+                _ => return Ok(atom),
+            };
+
+            let greedy = !self.eat('?');
+
+            atom = Node::Repeat {
+                node: Box::new(atom),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    // Parse `{n}`, `{n,}` or `{n,m}`, returns `None` when syntax is wrong
+    fn parse_counts(&mut self) -> Result<Option<(u32, Option<u32>)>, RegexError> {
+        let rest = &self.pattern[self.position + 1..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+
+        let body = &rest[..end];
+        let parse = |s: &str| s.trim().parse::<u32>().ok();
+
+        let counts = match body.split_once(',') {
+            None => parse(body).map(|n| (n, Some(n))),
+            Some((min, "")) => parse(min).map(|n| (n, None)),
+            Some((min, max)) => match (parse(min), parse(max)) {
+                (Some(min), Some(max)) => Some((min, Some(max))),
+                _ => None,
+            },
+        };
+
+        let (min, max) = match counts {
+            Some(counts) => counts,
+            None => return Ok(None),
+        };
+
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("invalid repetition range"));
+        }
+        if min > MAX_REPEAT || max.is_some_and(|max| max > MAX_REPEAT) {
+            return Err(self.error("repetition count is too large"));
+        }
+
+        // Skip `{`, body and `}`
+        self.position += end + 2;
+
+        Ok(Some((min, max)))
+    }
+}
+
+// Classes which could be written with backslash, e.g. `\d`
+fn shorthand_class(c: char) -> Option<ClassItem> {
+    match c {
+        'w' => Some(ClassItem::Word(false)),
+        'W' => Some(ClassItem::Word(true)),
+        'd' => Some(ClassItem::Digit(false)),
+        'D' => Some(ClassItem::Digit(true)),
+        's' => Some(ClassItem::Space(false)),
+        'S' => Some(ClassItem::Space(true)),
+        _ => None,
+    }
+}
+
+/*
+  Instructions of compiled program, which
+  is executed by Pike virtual machine
+*/
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(CharClass),
+    Assert(Assertion),
+    Split(usize, usize),
+    Jmp(usize),
+    Save(usize),
+    Match,
+}
+
+/*
+  Compiler from the syntax tree to the list of instructions
+*/
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize, RegexError> {
+        if self.program.len() >= MAX_PROGRAM_SIZE {
+            return Err(RegexError {
+                message: "pattern is too large",
+                position: 0,
+            });
+        }
+
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), RegexError> {
+        match node {
+            Node::Empty => {}
+            Node::Literal(c) => {
+                self.emit(Inst::Char(*c))?;
+            }
+            Node::Any => {
+                self.emit(Inst::Any)?;
+            }
+            Node::Class(class) => {
+                self.emit(Inst::Class(class.clone()))?;
+            }
+            Node::Assert(assertion) => {
+                self.emit(Inst::Assert(*assertion))?;
+            }
+            Node::Group(inner, index) => match index {
+                Some(index) => {
+                    self.emit(Inst::Save(index * 2))?;
+                    self.compile(inner)?;
+                    self.emit(Inst::Save(index * 2 + 1))?;
+                }
+                None => self.compile(inner)?,
+            },
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            Node::Alternate(branches) => {
+                let mut jumps = Vec::new();
+
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 == branches.len() {
+                        self.compile(branch)?;
+                        break;
+                    }
+
+                    let split = self.emit(Inst::Split(0, 0))?;
+                    self.compile(branch)?;
+                    jumps.push(self.emit(Inst::Jmp(0))?);
+                    self.program[split] = Inst::Split(split + 1, self.program.len());
+                }
+
+                let end = self.program.len();
+                for jump in jumps {
+                    self.program[jump] = Inst::Jmp(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.compile(node)?;
+                }
+
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.compile(node)?;
+                        self.emit(Inst::Jmp(split))?;
+                        self.program[split] = self.split(split + 1, self.program.len(), *greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0))?);
+                            self.compile(node)?;
+                        }
+
+                        let end = self.program.len();
+                        for split in splits {
+                            self.program[split] = self.split(split + 1, end, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Greedy repetition prefers to take one more iteration, lazy one prefers to leave
+    fn split(&self, body: usize, out: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(body, out)
+        } else {
+            Inst::Split(out, body)
+        }
+    }
+}
+
+/*
+  Positions of the whole match (group 0)
+  and each capture group, as byte ranges
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Captures {
+    slots: Vec<Option<usize>>,
+}
+
+impl Captures {
+    /*
+      Byte range of the capture group,
+      `None` if group didn't participate in match
+    */
+    pub fn get(&self, index: usize) -> Option<(usize, usize)> {
+        match (self.slots.get(index * 2)?, self.slots.get(index * 2 + 1)?) {
+            (Some(start), Some(end)) => Some((*start, *end)),
+            _ => None,
+        }
+    }
+
+    // Amount of groups, including the whole match
+    pub fn len(&self) -> usize {
+        self.slots.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+/*
+  List of running threads for single step of virtual machine,
+  `seen` keeps generation when instruction was last added to avoid duplicates
+*/
+struct Threads {
+    list: Vec<(usize, Vec<Option<usize>>)>,
+    seen: Vec<usize>,
+    generation: usize,
+}
+
+impl Threads {
+    fn new(size: usize) -> Threads {
+        Threads {
+            list: Vec::new(),
+            seen: vec![0; size],
+            generation: 1,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.generation += 1;
+    }
+
+    // Mark instruction as visited, returns false if it was already visited on this step
+    fn visit(&mut self, pc: usize) -> bool {
+        if self.seen[pc] == self.generation {
+            false
+        } else {
+            self.seen[pc] = self.generation;
+            true
+        }
+    }
+}
+
+/*
+  Compiled regular expression, compile it once with `Regex::new`
+  and reuse it for every searched line
+*/
+#[derive(Debug, Clone)]
+pub struct Regex {
+    pattern: String,
+    program: Vec<Inst>,
+    groups: usize,
+    case_sensitive: bool,
+}
+
+impl Regex {
+    /*
+      Parse and compile the pattern, supports:
+      literals, `.`, classes (`[a-z]`, `[^a]`, `\w`, `\d`, `\s`),
+      anchors (`^`, `$`, `\b`), alternation (`|`), groups (`(..)`, `(?:..)`)
+      and repetition (`*`, `+`, `?`, `{n,m}`, lazy variants with `?`)
+    */
+    pub fn new(pattern: &str, case_sensitive: bool) -> Result<Regex, RegexError> {
+        let (node, groups) = Parser::new(pattern).parse()?;
+
+        let mut compiler = Compiler {
+            program: Vec::new(),
+        };
+        compiler.emit(Inst::Save(0))?;
+        compiler.compile(&node)?;
+        compiler.emit(Inst::Save(1))?;
+        compiler.emit(Inst::Match)?;
+
+        Ok(Regex {
+            pattern: pattern.to_string(),
+            program: compiler.program,
+            groups,
+            case_sensitive,
+        })
+    }
+
+    // Source pattern of the regular expression
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    // Amount of capture groups, without the whole match
+    pub fn group_count(&self) -> usize {
+        self.groups
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.find_at(text, 0).is_some()
+    }
+
+    // Find leftmost match, which starts at `start` byte or later
+    pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        self.captures_at(text, start)?.get(0)
+    }
+
+    pub fn captures_at(&self, text: &str, start: usize) -> Option<Captures> {
+        self.execute(text, start).map(|slots| Captures { slots })
+    }
+
+    // Iterate over all non overlapping matches in the text
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> Matches<'r, 't> {
+        Matches {
+            regex: self,
+            text,
+            position: 0,
+        }
+    }
+
+    /*
+      Run Pike virtual machine: all threads move through the text at once,
+      so matching time is linear to the length of the text
+    */
+    fn execute(&self, text: &str, start: usize) -> Option<Vec<Option<usize>>> {
+        let slots = (self.groups + 1) * 2;
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut matched = None;
+        let mut position = start;
+
+        loop {
+            // Start a new attempt at every position, until something is matched
+            if matched.is_none() {
+                self.add_thread(&mut current, 0, vec![None; slots], text, position);
+            }
+
+            // Nothing is running anymore, and the match is already found
+            if current.list.is_empty() && matched.is_some() {
+                break;
+            }
+
+            let c = text[position..].chars().next();
+            let next_position = position + c.map_or(0, char::len_utf8);
+
+            for (pc, slots) in current.list.drain(..) {
+                let consumed = match &self.program[pc] {
+                    Inst::Match => {
+                        // Threads after this one have lower priority, so drop them
+                        matched = Some(slots);
+                        break;
+                    }
+                    Inst::Char(expected) => c.is_some_and(|c| self.char_eq(*expected, c)),
+                    Inst::Any => c.is_some_and(|c| c != '\n'),
+                    Inst::Class(class) => c.is_some_and(|c| class.matches(c, self.case_sensitive)),
+                    _ => false,
+                };
+
+                if consumed {
+                    self.add_thread(&mut next, pc + 1, slots, text, next_position);
+                }
+            }
+
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+
+            if c.is_none() {
+                break;
+            }
+            position = next_position;
+        }
+
+        matched
+    }
+
+    /*
+      Follow all jumps, splits and assertions, so list contains only consuming instructions.
+      Explicit stack is used instead of recursion, long chains of splits can't overflow it,
+      first branch of the split is pushed last, so threads keep their priority order
+    */
+    fn add_thread(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        slots: Vec<Option<usize>>,
+        text: &str,
+        position: usize,
+    ) {
+        let mut stack = vec![(pc, slots)];
+
+        while let Some((pc, mut slots)) = stack.pop() {
+            if !threads.visit(pc) {
+                continue;
+            }
+
+            match &self.program[pc] {
+                Inst::Jmp(target) => stack.push((*target, slots)),
+                Inst::Split(first, second) => {
+                    stack.push((*second, slots.clone()));
+                    stack.push((*first, slots));
+                }
+                Inst::Save(slot) => {
+                    slots[*slot] = Some(position);
+                    stack.push((pc + 1, slots));
+                }
+                Inst::Assert(assertion) => {
+                    if check_assertion(*assertion, text, position) {
+                        stack.push((pc + 1, slots));
+                    }
+                }
+                _ => threads.list.push((pc, slots)),
+            }
+        }
+    }
+
+    fn char_eq(&self, expected: char, c: char) -> bool {
+        expected == c || (!self.case_sensitive && fold(expected) == fold(c))
+    }
+}
+
+/*
+  Iterator over non overlapping matches,
+  created with `Regex::find_iter`
+*/
+pub struct Matches<'r, 't> {
+    regex: &'r Regex,
+    text: &'t str,
+    position: usize,
+}
+
+impl<'r, 't> Iterator for Matches<'r, 't> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        if self.position > self.text.len() {
+            return None;
+        }
+
+        let (start, end) = self.regex.find_at(self.text, self.position)?;
+
+        // Empty match must move forward, otherwise we loop forever
+        self.position = if start == end {
+            end + self.text[end..].chars().next().map_or(1, char::len_utf8)
+        } else {
+            end
+        };
+
+        Some((start, end))
+    }
+}
+
+/*
+  Escape all special characters in the text,
+  so it could be matched literally inside of pattern
+*/
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn check_assertion(assertion: Assertion, text: &str, position: usize) -> bool {
+    match assertion {
+        Assertion::Start => position == 0,
+        Assertion::End => position == text.len(),
+        Assertion::WordBoundary => is_word_boundary(text, position),
+        Assertion::NotWordBoundary => !is_word_boundary(text, position),
+    }
+}
+
+fn is_word_boundary(text: &str, position: usize) -> bool {
+    let before = text[..position]
+        .chars()
+        .next_back()
+        .is_some_and(is_word_char);
+    let after = text[position..].chars().next().is_some_and(is_word_char);
+
+    before != after
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Option<(usize, usize)> {
+        Regex::new(pattern, true).unwrap().find_at(text, 0)
+    }
+
+    #[test]
+    fn literals_and_classes() {
+        assert_eq!(Some((2, 5)), find("fox", "a fox"));
+        assert_eq!(Some((0, 3)), find("[a-c]+", "abcd"));
+        assert_eq!(Some((1, 2)), find("[^a]", "ab"));
+        assert_eq!(Some((3, 5)), find(r"\d\d", "ab 42"));
+        assert_eq!(Some((0, 3)), find(r"a.c", "abc"));
+        assert_eq!(None, find(r"a.c", "a\nc"));
+    }
+
+    #[test]
+    fn anchors() {
+        assert_eq!(Some((0, 7)), find(r"^fn \w+", "fn main() {}"));
+        assert_eq!(None, find(r"^fn \w+", "  fn main() {}"));
+        assert_eq!(Some((5, 6)), find("}$", "{ }{ }"));
+        assert_eq!(Some((4, 7)), find(r"\bcat\b", "the cat sat"));
+        assert_eq!(None, find(r"\bcat\b", "concatenate"));
+    }
+
+    #[test]
+    fn alternation_and_groups() {
+        let regex = Regex::new("(foo|ba(r|z))+", true).unwrap();
+        let captures = regex.captures_at("xxbazfoo!", 0).unwrap();
+
+        assert_eq!(Some((2, 8)), captures.get(0));
+        assert_eq!(Some((5, 8)), captures.get(1));
+        assert_eq!(Some((4, 5)), captures.get(2));
+        assert_eq!(3, captures.len());
+    }
+
+    #[test]
+    fn repetition() {
+        assert_eq!(Some((0, 3)), find("a{2,3}", "aaaa"));
+        assert_eq!(Some((0, 2)), find("a{2}", "aaaa"));
+        assert_eq!(Some((0, 4)), find("a{2,}", "aaaa"));
+        assert_eq!(Some((0, 3)), find("<.+?>", "<a><b>"));
+        assert_eq!(Some((0, 6)), find("<.+>", "<a><b>"));
+        assert_eq!(Some((0, 0)), find("x*", "abc"));
+
+        // Braces without counts are literals
+        assert_eq!(Some((0, 11)), find(r"fn \w+\(\) {", "fn main() {}"));
+        assert_eq!(Some((0, 3)), find("{a}", "{a}"));
+        assert_eq!(Some((1, 4)), find("a{x", "ba{x"));
+        assert_eq!(Some((0, 5)), find("a{,2}", "a{,2}"));
+    }
+
+    #[test]
+    fn large_repeat_of_empty_match() {
+        // Every repeat is a chain of splits, which are followed without consuming a char
+        let regex = Regex::new("((a?){100}){200}b", true).unwrap();
+
+        assert_eq!(Some((0, 4)), regex.find_at("aaab", 0));
+        assert!(!regex.is_match("aaa"));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let regex = Regex::new("ru[s]t", false).unwrap();
+
+        assert!(regex.is_match("Trust me."));
+        assert!(regex.is_match("RUST"));
+    }
+
+    #[test]
+    fn find_iter_skips_empty_matches() {
+        let regex = Regex::new("a*", true).unwrap();
+        let matches: Vec<_> = regex.find_iter("baa").collect();
+
+        assert_eq!(vec![(0, 0), (1, 3), (3, 3)], matches);
+    }
+
+    #[test]
+    fn escaped_text_is_literal() {
+        let regex = Regex::new(&escape("a.b*(c)"), true).unwrap();
+
+        assert!(regex.is_match("xa.b*(c)"));
+        assert!(!regex.is_match("axbbc"));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert_eq!(
+            "unclosed group",
+            Regex::new("(ab", true).unwrap_err().message
+        );
+        assert_eq!(
+            "unmatched closing parenthesis",
+            Regex::new("ab)", true).unwrap_err().message
+        );
+        assert_eq!(
+            "repetition operator without operand",
+            Regex::new("*a", true).unwrap_err().message
+        );
+        assert_eq!(
+            "repetition operator without operand",
+            Regex::new("{2}a", true).unwrap_err().message
+        );
+        assert_eq!(
+            "unclosed character class",
+            Regex::new("[ab", true).unwrap_err().message
+        );
+        assert_eq!(
+            "pattern is too large",
+            Regex::new("((a{1000}){1000}){1000}", true)
+                .unwrap_err()
+                .message
+        );
+    }
+}
@@ -0,0 +1,437 @@
+// Search and replace mode, turned on with `--replace` flag
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use colored::Colorize;
+
+use crate::output::paint;
+use crate::{walk, Config, Matcher};
+
+/*
+  Piece of the replacement text: plain text
+  or reference to the capture group (`$1`, `${1}`)
+*/
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Text(String),
+    Group(usize),
+}
+
+/*
+  Replacement text, parsed once before search,
+  group references are parsed only in regex mode
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replacement {
+    pieces: Vec<Piece>,
+}
+
+impl Replacement {
+    /*
+      Parse replacement text: `$0` is the whole match, `$1` or `${1}`
+      is the first group and `$$` is a single `$`, other `$` are kept as is
+    */
+    pub fn new(text: &str, groups: bool) -> Replacement {
+        if !groups {
+            return Replacement {
+                pieces: vec![Piece::Text(text.to_string())],
+            };
+        }
+
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut rest = text;
+
+        while let Some(index) = rest.find('$') {
+            literal.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+
+            let (group, len) = if let Some(braced) = rest.strip_prefix('{') {
+                match braced.find('}') {
+                    Some(end) => (braced[..end].parse::<usize>().ok(), end + 2),
+                    None => (None, 0),
+                }
+            } else {
+                let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+                (rest[..digits].parse::<usize>().ok(), digits)
+            };
+
+            match group {
+                Some(group) => {
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Group(group));
+                    rest = &rest[len..];
+                }
+                None if rest.starts_with('$') => {
+                    literal.push('$');
+                    rest = &rest[1..];
+                }
+                None => literal.push('$'),
+            }
+        }
+
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+
+        Replacement { pieces }
+    }
+
+    /*
+      Append replacement for a single match to `out`,
+      groups, which didn't participate in match, are empty
+    */
+    fn expand(&self, line: &str, groups: &[Option<(usize, usize)>], out: &mut String) {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Group(group) => {
+                    if let Some(Some((start, end))) = groups.get(*group) {
+                        out.push_str(&line[*start..*end]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/*
+  Replace every match in the line,
+  returns `None` when the line stays the same
+*/
+pub fn replace_line(matcher: &Matcher, replacement: &Replacement, line: &str) -> Option<String> {
+    let mut replaced = String::with_capacity(line.len());
+    let mut position = 0;
+
+    for (start, end) in matcher.find_all(line) {
+        replaced.push_str(&line[position..start]);
+
+        let groups = matcher
+            .captures_at(line, start)
+            .unwrap_or_else(|| vec![Some((start, end))]);
+        replacement.expand(line, &groups, &mut replaced);
+
+        position = end;
+    }
+    replaced.push_str(&line[position..]);
+
+    Some(replaced).filter(|replaced| replaced != line)
+}
+
+/*
+  Single changed line: its number,
+  text before and after replacement
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub line_number: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/*
+  Replace matches in every line of contents, line endings
+  (`\n` or `\r\n`) are kept, returns new contents and list of changes
+*/
+pub fn replace_contents(
+    matcher: &Matcher,
+    replacement: &Replacement,
+    contents: &str,
+) -> (String, Vec<Change>) {
+    let mut replaced = String::with_capacity(contents.len());
+    let mut changes = Vec::new();
+
+    for (index, line) in contents.split_inclusive('\n').enumerate() {
+        let body = line.strip_suffix('\n').unwrap_or(line);
+        let body = body.strip_suffix('\r').unwrap_or(body);
+        let ending = &line[body.len()..];
+
+        match replace_line(matcher, replacement, body) {
+            Some(new) => {
+                replaced.push_str(&new);
+                changes.push(Change {
+                    line_number: index + 1,
+                    old: body.to_string(),
+                    new,
+                });
+            }
+            None => replaced.push_str(body),
+        }
+        replaced.push_str(ending);
+    }
+
+    (replaced, changes)
+}
+
+/*
+  Replace matches in a single file (or standard input, which is `-`):
+  preview changes as a diff, or write them to the file with `--in-place`.
+  Binary files and files with invalid UTF-8 are never changed,
+  returns amount of changed lines
+*/
+pub fn replace_input<W: Write>(
+    config: &Config,
+    replacement: &Replacement,
+    path: &Path,
+    skip_binary: bool,
+    out: &mut W,
+) -> io::Result<usize> {
+    let stdin = path == Path::new("-");
+
+    let bytes = if stdin {
+        if config.in_place {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "standard input can't be changed in place",
+            ));
+        }
+
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        fs::read(path)?
+    };
+
+    if walk::is_binary(&bytes) {
+        if skip_binary {
+            return Ok(0);
+        }
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "binary file, nothing is replaced",
+        ));
+    }
+
+    let contents = String::from_utf8(bytes).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "file isn't valid UTF-8, nothing is replaced",
+        )
+    })?;
+
+    let (replaced, changes) = replace_contents(&config.matcher, replacement, &contents);
+    if changes.is_empty() {
+        return Ok(0);
+    }
+
+    if config.in_place {
+        write_atomically(path, replaced.as_bytes())?;
+        writeln!(
+            out,
+            "{}: {} line(s) replaced",
+            path.display(),
+            changes.len()
+        )?;
+    } else {
+        let path = if stdin {
+            Path::new("(standard input)")
+        } else {
+            path
+        };
+        print_preview(path, &changes, config.color.enabled(), out)?;
+    }
+
+    Ok(changes.len())
+}
+
+/*
+  Print changes in unified diff style,
+  every changed line is its own hunk
+*/
+fn print_preview<W: Write>(
+    path: &Path,
+    changes: &[Change],
+    color: bool,
+    out: &mut W,
+) -> io::Result<()> {
+    let path = path.display().to_string();
+    let header = |sign: &str| paint(&format!("{} {}", sign, path), color, |text| text.bold());
+
+    writeln!(out, "{}", header("---"))?;
+    writeln!(out, "{}", header("+++"))?;
+
+    for change in changes {
+        let hunk = format!("@@ -{0} +{0} @@", change.line_number);
+        let old = format!("-{}", change.old);
+        let new = format!("+{}", change.new);
+
+        writeln!(out, "{}", paint(&hunk, color, |text| text.cyan()))?;
+        writeln!(out, "{}", paint(&old, color, |text| text.red()))?;
+        writeln!(out, "{}", paint(&new, color, |text| text.green()))?;
+    }
+
+    Ok(())
+}
+
+/*
+  Write contents into temporary file next to the original one,
+  and rename it over the original, so the file is never half written.
+  Symbolic link is followed, so the file it points to is replaced, not the link,
+  permissions and owner of the original file (when it exists) are kept
+*/
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let temp_path = temp_path(&path);
+    let metadata = fs::metadata(&path).ok();
+
+    let written = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        if let Some(metadata) = &metadata {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+            keep_owner(&temp_path, metadata);
+        }
+        fs::rename(&temp_path, &path)
+    })();
+
+    // Don't leave temporary file behind, when something failed
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    written
+}
+
+/*
+  Give the file the owner of the original one, only privileged user
+  can give files away, so failure leaves the file owned by us, like `sed -i` does
+*/
+#[cfg(unix)]
+fn keep_owner(path: &Path, original: &fs::Metadata) {
+    use std::os::unix::fs::{chown, MetadataExt};
+
+    let _ = chown(path, Some(original.uid()), Some(original.gid()));
+}
+
+#[cfg(not(unix))]
+fn keep_owner(_path: &Path, _original: &fs::Metadata) {}
+
+// Hidden file in the same directory, so rename never crosses file systems
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map_or(String::from("file"), |name| {
+        name.to_string_lossy().into_owned()
+    });
+
+    path.with_file_name(format!(".{}.minigrep-{}", name, process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn matcher(query: &str, regex: bool) -> Matcher {
+        Matcher::new(query, regex, false, true).unwrap()
+    }
+
+    #[test]
+    fn group_references() {
+        let replacement = Replacement::new("$2-${1}x $$3 $0 $ ${4", true);
+        let matcher = matcher(r"(\w+)@(\w+)", true);
+
+        assert_eq!(
+            Some(String::from("b-ax $3 a@b $ ${4!")),
+            replace_line(&matcher, &replacement, "a@b!")
+        );
+    }
+
+    #[test]
+    fn literal_replacement() {
+        let replacement = Replacement::new("$1", false);
+        let matcher = matcher("cat", false);
+
+        assert_eq!(
+            Some(String::from("$1 and $1")),
+            replace_line(&matcher, &replacement, "cat and cat")
+        );
+        assert_eq!(None, replace_line(&matcher, &replacement, "dog"));
+    }
+
+    #[test]
+    fn contents_keep_line_endings() {
+        let replacement = Replacement::new("dog", false);
+        let (replaced, changes) =
+            replace_contents(&matcher("cat", false), &replacement, "a cat\r\nno\ncat");
+
+        assert_eq!("a dog\r\nno\ndog", replaced);
+        assert_eq!(
+            vec![1, 3],
+            changes
+                .iter()
+                .map(|change| change.line_number)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn preview_and_in_place() {
+        let path = env::temp_dir().join(format!("minigrep-replace-{}.txt", process::id()));
+        fs::write(&path, "one cat\ntwo\n").unwrap();
+        let file = path.display().to_string();
+
+        let args = [
+            "minigrep",
+            "--color=never",
+            "--replace",
+            "dog",
+            "cat",
+            &file,
+        ];
+        let mut config =
+            Config::with_defaults(Vec::new(), args.iter().map(|arg| arg.to_string())).unwrap();
+        let replacement = config.replace.clone().unwrap();
+
+        let mut out = Vec::new();
+        replace_input(&config, &replacement, &path, false, &mut out).unwrap();
+        assert_eq!(
+            format!("--- {0}\n+++ {0}\n@@ -1 +1 @@\n-one cat\n+one dog\n", file),
+            String::from_utf8(out).unwrap()
+        );
+        assert_eq!("one cat\ntwo\n", fs::read_to_string(&path).unwrap());
+
+        config.in_place = true;
+        replace_input(&config, &replacement, &path, false, &mut Vec::new()).unwrap();
+        assert_eq!("one dog\ntwo\n", fs::read_to_string(&path).unwrap());
+
+        // Binary file is refused and stays the same
+        fs::write(&path, b"cat\0").unwrap();
+        assert!(replace_input(&config, &replacement, &path, false, &mut Vec::new()).is_err());
+        assert_eq!(b"cat\0".to_vec(), fs::read(&path).unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn in_place_keeps_links_and_permissions() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = env::temp_dir().join(format!("minigrep-replace-link-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.txt");
+        let link = dir.join("link.txt");
+        fs::write(&target, "cat\n").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        write_atomically(&link, b"dog\n").unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!("dog\n", fs::read_to_string(&target).unwrap());
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(0o640, mode & 0o777);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,373 @@
+// Recursive directory walking, which honors `.gitignore` and `.ignore` files
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Ignore files, which are read in every visited directory (later one wins)
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+// Amount of bytes, which are checked to detect a binary file
+const BINARY_CHECK_LEN: usize = 8 * 1024;
+
+/*
+  Single rule from the ignore file,
+  e.g. `target/`, `*.log` or `!keep.log`
+*/
+#[derive(Debug, Clone, PartialEq)]
+struct IgnoreRule {
+    pattern: Vec<char>,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    /*
+      Parse one line of ignore file,
+      returns `None` for empty lines and comments
+    */
+    fn parse(line: &str) -> Option<IgnoreRule> {
+        let mut line = line.trim_end();
+
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        // Leading `\` escapes `!` and `#`, which are special otherwise
+        let negated = line.starts_with('!');
+        if negated || line.starts_with("\\!") || line.starts_with("\\#") {
+            line = &line[1..];
+        }
+
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+
+        // Pattern with slash inside is relative to the directory of ignore file
+        let anchored = line.contains('/');
+        let line = line.trim_start_matches('/');
+
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(IgnoreRule {
+            pattern: line.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    // Check rule against path, relative to the directory of ignore file
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        let text: Vec<char> = if self.anchored {
+            relative.chars().collect()
+        } else {
+            // Pattern without slash matches a name on any level
+            relative
+                .rsplit('/')
+                .next()
+                .unwrap_or(relative)
+                .chars()
+                .collect()
+        };
+
+        glob_match(&self.pattern, &text)
+    }
+}
+
+/*
+  Rules loaded from ignore files of one directory,
+  `base` is the directory, where they were found
+*/
+#[derive(Debug)]
+struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+    fn load(base: &Path, name: &str) -> Option<IgnoreFile> {
+        let contents = fs::read_to_string(base.join(name)).ok()?;
+        let rules: Vec<IgnoreRule> = contents.lines().filter_map(IgnoreRule::parse).collect();
+
+        if rules.is_empty() {
+            return None;
+        }
+
+        Some(IgnoreFile {
+            base: base.to_path_buf(),
+            rules,
+        })
+    }
+}
+
+/*
+  Match glob pattern against the text:
+  `*` and `?` don't cross `/`, `**` matches any amount of directories,
+  `[a-z]` and `[!a-z]` are character classes, `\` escapes next character
+*/
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+
+            match rest.first() {
+                // Trailing `**` matches everything inside
+                None => true,
+                // `**/` matches zero or more directories
+                Some('/') => {
+                    let rest = &rest[1..];
+
+                    glob_match(rest, text)
+                        || (0..text.len())
+                            .any(|i| text[i] == '/' && glob_match(rest, &text[i + 1..]))
+                }
+                // Otherwise it is the same as a single `*`
+                Some(_) => glob_match(&pattern[1..], text),
+            }
+        }
+        Some('*') => {
+            for i in 0..=text.len() {
+                if glob_match(&pattern[1..], &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match text.first() {
+            Some(c) if *c != '/' => glob_match(&pattern[1..], &text[1..]),
+            _ => false,
+        },
+        Some('[') => match (class_match(&pattern[1..], text.first()), text.first()) {
+            (Some((true, len)), Some(_)) => glob_match(&pattern[len + 1..], &text[1..]),
+            (Some(_), _) => false,
+            // Class without closing bracket is a plain character
+            (None, Some('[')) => glob_match(&pattern[1..], &text[1..]),
+            (None, _) => false,
+        },
+        Some('\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..])
+        }
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/*
+  Match character class, which starts right after `[`,
+  returns whether it matched and the length of the class with `]`
+*/
+fn class_match(pattern: &[char], c: Option<&char>) -> Option<(bool, usize)> {
+    let negated = matches!(pattern.first(), Some('!') | Some('^'));
+    let mut i = if negated { 1 } else { 0 };
+    let mut found = false;
+    let mut first = true;
+
+    loop {
+        let low = *pattern.get(i)?;
+
+        if low == ']' && !first {
+            break;
+        }
+        first = false;
+
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|high| *high != ']') {
+            let high = pattern[i + 2];
+            found |= c.is_some_and(|c| low <= *c && *c <= high);
+            i += 3;
+        } else {
+            found |= c == Some(&low);
+            i += 1;
+        }
+    }
+
+    let matched = c.is_some_and(|c| *c != '/') && found != negated;
+    Some((matched, i + 1))
+}
+
+/*
+  Check all loaded ignore files from the top directory to the deepest one,
+  the last matched rule decides, so `!pattern` could bring file back
+*/
+fn is_ignored(ignores: &[IgnoreFile], path: &Path, is_dir: bool) -> bool {
+    let mut ignored = false;
+
+    for ignore in ignores {
+        let relative = match path.strip_prefix(&ignore.base) {
+            Ok(relative) => relative,
+            Err(_) => continue,
+        };
+
+        let relative: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let relative = relative.join("/");
+
+        for rule in &ignore.rules {
+            if rule.matches(&relative, is_dir) {
+                ignored = !rule.negated;
+            }
+        }
+    }
+
+    ignored
+}
+
+/*
+  Collect all files, which should be searched:
+  file itself, or every not ignored file inside of directory (sorted by path)
+*/
+pub fn files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    if root.is_dir() {
+        walk_dir(root, &mut Vec::new(), &mut files)?;
+    } else {
+        files.push(root.to_path_buf());
+    }
+
+    Ok(files)
+}
+
+fn walk_dir(dir: &Path, ignores: &mut Vec<IgnoreFile>, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    // Ignore files of this directory are active only inside of it
+    let loaded = ignores.len();
+    for name in IGNORE_FILES {
+        if let Some(ignore) = IgnoreFile::load(dir, name) {
+            ignores.push(ignore);
+        }
+    }
+
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+
+        // Follow symbolic links only to files, so we can't loop forever
+        let file_type = entry.file_type()?;
+        let is_dir = file_type.is_dir();
+        if file_type.is_symlink() && !path.is_file() {
+            continue;
+        }
+
+        if (is_dir && entry.file_name() == ".git") || is_ignored(ignores, &path, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            // Directory, which we can't read, shouldn't stop the whole search
+            if let Err(error) = walk_dir(&path, ignores, files) {
+                eprintln!("{}: {}", path.display(), error);
+            }
+        } else {
+            files.push(path);
+        }
+    }
+
+    ignores.truncate(loaded);
+    Ok(())
+}
+
+/*
+  Detect binary file the same way as git does,
+  by looking for zero byte at the beginning of the file
+*/
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(BINARY_CHECK_LEN).any(|byte| *byte == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+
+        glob_match(&pattern, &text)
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob("*.log", "debug.log"));
+        assert!(!glob("*.log", "logs/debug.log"));
+        assert!(glob("logs/**/*.log", "logs/debug.log"));
+        assert!(glob("logs/**/*.log", "logs/a/b/debug.log"));
+        assert!(glob("**/build", "a/b/build"));
+        assert!(glob("build/**", "build/a/b"));
+        assert!(glob("file?.txt", "file1.txt"));
+        assert!(glob("file[0-9].txt", "file7.txt"));
+        assert!(!glob("file[!0-9].txt", "file7.txt"));
+        assert!(glob(r"\*.txt", "*.txt"));
+    }
+
+    #[test]
+    fn ignore_rules() {
+        let rule = IgnoreRule::parse("target/").unwrap();
+        assert!(rule.matches("target", true));
+        assert!(rule.matches("nested/target", true));
+        assert!(!rule.matches("target", false));
+
+        let rule = IgnoreRule::parse("/docs/*.md").unwrap();
+        assert!(rule.matches("docs/index.md", false));
+        assert!(!rule.matches("src/docs/index.md", false));
+
+        assert_eq!(None, IgnoreRule::parse("# comment"));
+        assert_eq!(None, IgnoreRule::parse("   "));
+        assert!(IgnoreRule::parse("!keep.log").unwrap().negated);
+    }
+
+    #[test]
+    fn walk_honors_ignore_files() {
+        let root = env::temp_dir().join(format!("minigrep_walk_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+
+        fs::write(root.join(".gitignore"), "target/\n*.log\n!keep.log\n").unwrap();
+        fs::write(root.join("src/.ignore"), "skip.rs\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/skip.rs"), "fn skip() {}").unwrap();
+        fs::write(root.join("src/nested/lib.rs"), "pub fn lib() {}").unwrap();
+        fs::write(root.join("target/out.rs"), "fn out() {}").unwrap();
+        fs::write(root.join("debug.log"), "debug").unwrap();
+        fs::write(root.join("keep.log"), "keep").unwrap();
+
+        let found: Vec<PathBuf> = files(&root)
+            .unwrap()
+            .iter()
+            .map(|path| path.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        fs::remove_dir_all(&root).unwrap();
+
+        let expected: Vec<PathBuf> = [
+            ".gitignore",
+            "keep.log",
+            "src/.ignore",
+            "src/main.rs",
+            "src/nested/lib.rs",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    fn binary_detection() {
+        assert!(is_binary(b"\x7fELF\x00\x01"));
+        assert!(!is_binary("plain text, привіт".as_bytes()));
+    }
+}
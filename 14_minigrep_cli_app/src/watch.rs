@@ -0,0 +1,340 @@
+// Watch mode: files are polled, and only new matching lines are printed (like `tail -f | grep`)
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::{gzip, match_line, output, walk, Config, Input};
+
+// Pause between two checks of watched files
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/*
+  Line, which was appended to the watched file:
+  its number, byte offset of its start and the text
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewLine {
+    pub line_number: usize,
+    pub offset: u64,
+    pub line: String,
+}
+
+/*
+  Watched file: how much of it is already read, and identity
+  of the file, so replaced file (after log rotation) is noticed,
+  positions are in decompressed contents, when the file is compressed
+*/
+#[derive(Debug)]
+pub struct Watched {
+    pub path: PathBuf,
+    skip_binary: bool,
+    identity: Option<(u64, u64)>,
+    size: u64, // Size of the file on the disk, when it was read last time
+    position: u64,
+    line_number: usize,
+    // Start of the last line, which isn't finished with `\n` yet
+    pending: Vec<u8>,
+    binary: bool,
+}
+
+impl Watched {
+    /*
+      Start watching the file from its current end, lines, which are
+      already in the file, are only counted, so line numbers stay right,
+      binary file is skipped (until it is replaced), when `skip_binary` is set
+    */
+    pub fn new(path: &Path, skip_binary: bool) -> io::Result<Watched> {
+        let mut watched = Watched {
+            path: path.to_path_buf(),
+            skip_binary,
+            identity: None,
+            size: 0,
+            position: 0,
+            line_number: 0,
+            pending: Vec::new(),
+            binary: false,
+        };
+
+        // File could be created later, then it is read from the start
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(watched),
+            Err(error) => return Err(error),
+        };
+        watched.identity = identity(&metadata);
+        watched.size = metadata.len();
+
+        let mut reader = contents(path, metadata.len(), 0)?;
+        if skip_binary && walk::is_binary(reader.fill_buf()?) {
+            watched.binary = true;
+            return Ok(watched);
+        }
+        let mut read_total = 0;
+
+        // Unfinished last line is left unread, so it is printed whole, when it is finished
+        loop {
+            let buffer = reader.fill_buf()?;
+            if buffer.is_empty() {
+                break;
+            }
+
+            for (index, byte) in buffer.iter().enumerate() {
+                if *byte == b'\n' {
+                    watched.line_number += 1;
+                    watched.position = read_total + index as u64 + 1;
+                }
+            }
+            let read = buffer.len();
+            read_total += read as u64;
+            reader.consume(read);
+        }
+
+        Ok(watched)
+    }
+
+    /*
+      Read everything appended since the last poll, returns finished lines,
+      truncated or replaced file is read again from the start
+    */
+    pub fn poll(&mut self) -> io::Result<Vec<NewLine>> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Rotated file could be removed for a moment, until the new one is created
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        let identity = identity(&metadata);
+        if identity != self.identity || metadata.len() < self.size {
+            self.identity = identity;
+            self.size = 0;
+            self.position = 0;
+            self.line_number = 0;
+            self.pending.clear();
+            self.binary = false;
+        }
+
+        if metadata.len() == self.size || self.binary {
+            return Ok(Vec::new());
+        }
+
+        let read_before = self.position + self.pending.len() as u64;
+        let mut reader = contents(&self.path, metadata.len(), read_before)?;
+        if read_before == 0 && self.skip_binary && walk::is_binary(reader.fill_buf()?) {
+            self.binary = true;
+            return Ok(Vec::new());
+        }
+        reader.read_to_end(&mut self.pending)?;
+        self.size = metadata.len();
+
+        let mut lines = Vec::new();
+        let finished = match self.pending.iter().rposition(|byte| *byte == b'\n') {
+            Some(index) => index + 1,
+            None => return Ok(lines),
+        };
+
+        for bytes in self.pending[..finished].split_inclusive(|byte| *byte == b'\n') {
+            let offset = self.position;
+            self.position += bytes.len() as u64;
+            self.line_number += 1;
+
+            let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+            let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+
+            lines.push(NewLine {
+                line_number: self.line_number,
+                offset,
+                line: String::from_utf8_lossy(bytes).into_owned(),
+            });
+        }
+        self.pending.drain(..finished);
+
+        Ok(lines)
+    }
+}
+
+/*
+  Contents of the file after `start` bytes (of decompressed contents), only the first
+  `size` bytes of the file are read, compressed file is decompressed from its start again
+*/
+fn contents(path: &Path, size: u64, start: u64) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    if gzip::is_gzip(reader.fill_buf()?) {
+        let mut decoded = gzip::decompressed(reader.take(size))?;
+        io::copy(&mut decoded.by_ref().take(start), &mut io::sink())?;
+        return Ok(decoded);
+    }
+
+    reader.seek(SeekFrom::Start(start))?;
+    Ok(Box::new(reader.take(size.saturating_sub(start))))
+}
+
+// Device and inode of the file, other platforms rely on the size only
+#[cfg(unix)]
+fn identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/*
+  Keep polling the files forever, printing new matching lines,
+  errors of a single file are printed, but don't stop watching
+*/
+pub(crate) fn run(config: &Config, inputs: &[Input], with_path: bool) -> io::Result<()> {
+    let mut watched = Vec::with_capacity(inputs.len());
+
+    for input in inputs {
+        if input.path == Path::new("-") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "standard input can't be watched",
+            ));
+        }
+        watched.push(Watched::new(&input.path, input.skip_binary)?);
+    }
+
+    let mut out = io::stdout().lock();
+
+    loop {
+        for file in &mut watched {
+            let lines = match file.poll() {
+                Ok(lines) => lines,
+                Err(error) => {
+                    eprintln!("{}: {}", file.path.display(), error);
+                    continue;
+                }
+            };
+
+            for new_line in &lines {
+                let offset = new_line.offset as usize;
+
+                if let Some(found) =
+                    match_line(config, new_line.line_number, offset, &new_line.line)
+                {
+                    output::print_match(config, &file.path, with_path, &found, &mut out)?;
+                }
+            }
+        }
+
+        out.flush()?;
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::OpenOptions;
+
+    fn append(path: &Path, text: &str) {
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    fn lines(watched: &mut Watched) -> Vec<(usize, u64, String)> {
+        watched
+            .poll()
+            .unwrap()
+            .into_iter()
+            .map(|new| (new.line_number, new.offset, new.line))
+            .collect()
+    }
+
+    #[test]
+    fn new_lines_truncation_and_rotation() {
+        let dir = env::temp_dir().join(format!("minigrep-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        fs::write(&path, "old\nunfinished").unwrap();
+
+        let mut watched = Watched::new(&path, false).unwrap();
+        assert!(lines(&mut watched).is_empty());
+
+        append(&path, " line\nnew\r\nhalf");
+        assert_eq!(
+            vec![
+                (2, 4, String::from("unfinished line")),
+                (3, 20, String::from("new")),
+            ],
+            lines(&mut watched)
+        );
+
+        append(&path, " done\n");
+        assert_eq!(
+            vec![(4, 25, String::from("half done"))],
+            lines(&mut watched)
+        );
+
+        // Truncated file is read from the start
+        fs::write(&path, "fresh\n").unwrap();
+        assert_eq!(vec![(1, 0, String::from("fresh"))], lines(&mut watched));
+
+        // Rotated file is replaced with the new one, even when it is bigger
+        if cfg!(unix) {
+            fs::rename(&path, dir.join("app.log.1")).unwrap();
+            assert!(lines(&mut watched).is_empty());
+
+            fs::write(&path, "rotated and longer\n").unwrap();
+            assert_eq!(
+                vec![(1, 0, String::from("rotated and longer"))],
+                lines(&mut watched)
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Member of gzip file with a single stored block
+    fn gzip_member(data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u16;
+
+        [
+            &[0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0xff][..],
+            &[0x01],
+            &len.to_le_bytes(),
+            &(!len).to_le_bytes(),
+            data,
+            &gzip::crc32(0, data).to_le_bytes(),
+            &(data.len() as u32).to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn compressed_and_binary_files() {
+        let dir = env::temp_dir().join(format!("minigrep-watch-gz-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // New members appended to compressed file are decompressed
+        let path = dir.join("app.log.gz");
+        fs::write(&path, gzip_member(b"old\n")).unwrap();
+        let mut watched = Watched::new(&path, true).unwrap();
+        assert!(lines(&mut watched).is_empty());
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&gzip_member(b"new\n")).unwrap();
+        assert_eq!(vec![(2, 4, String::from("new"))], lines(&mut watched));
+
+        // Binary file is skipped, unless it was given by user
+        let path = dir.join("data.bin");
+        fs::write(&path, "").unwrap();
+        let mut skipped = Watched::new(&path, true).unwrap();
+        let mut given = Watched::new(&path, false).unwrap();
+        append(&path, "line\0\n");
+        assert!(lines(&mut skipped).is_empty());
+        assert_eq!(vec![(1, 0, String::from("line\0"))], lines(&mut given));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,31 @@
+// Tests of the binary: output must be usable by other tools, nothing else is printed to stdout
+
+use std::process::Command;
+
+fn minigrep(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_minigrep_cli_app"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("--no-config")
+        .args(args)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn json_output_is_json_lines() {
+    let stdout = minigrep(&["--output", "json", "to", "poem.txt"]);
+    let first = stdout.lines().next().unwrap();
+
+    assert!(first.starts_with("{\"type\":\"begin\""), "{}", first);
+    assert!(stdout
+        .lines()
+        .all(|line| line.starts_with('{') && line.ends_with('}')));
+}
+
+#[test]
+fn count_is_the_only_output() {
+    assert_eq!("2\n", minigrep(&["-c", "to", "poem.txt"]));
+}
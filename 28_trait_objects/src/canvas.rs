@@ -0,0 +1,264 @@
+// Character-cell canvas: components draw characters into areas, anything outside of an area is clipped.
+
+use std::fmt;
+
+/* Rectangle of cells, `x` and `y` are the top left corner */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> u32 {
+        self.x.saturating_add(self.width)
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.y.saturating_add(self.height)
+    }
+
+    /* Common part of two rectangles, it is empty when they don't overlap */
+    pub fn intersect(&self, other: Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right()).max(x);
+        let bottom = self.bottom().min(other.bottom()).max(y);
+
+        Rect::new(x, y, right - x, bottom - y)
+    }
+}
+
+/* Grid of characters, the whole screen */
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    cells: Vec<char>, // Rows one after another, blank cell is a space
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas {
+            width,
+            height,
+            cells: vec![' '; width.saturating_mul(height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<char> {
+        if x < self.width && y < self.height {
+            Some(self.cells[self.index(x, y)])
+        } else {
+            None
+        }
+    }
+
+    /* Position of the cell in `cells`, counted in `usize`, so big canvases don't overflow */
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /* Area of the canvas to draw into, it is clipped by the canvas */
+    pub fn area(&mut self, rect: Rect) -> Area<'_> {
+        let clip = rect.intersect(Rect::new(0, 0, self.width, self.height));
+
+        Area {
+            canvas: self,
+            origin: (rect.x, rect.y),
+            size: (rect.width, rect.height),
+            clip,
+        }
+    }
+}
+
+/*
+  Rows of the canvas, spaces at the end of rows are trimmed,
+  so snapshots of the screen are easy to write down
+*/
+impl fmt::Display for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.cells.chunks(self.width.max(1) as usize) {
+            let row: String = row.iter().collect();
+            writeln!(f, "{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/*
+  Part of the canvas, which is given to a component: coordinates
+  are relative to its top left corner, and characters outside of it
+  (or outside of the canvas) are dropped
+*/
+pub struct Area<'a> {
+    canvas: &'a mut Canvas,
+    origin: (u32, u32), // Position of the area on the canvas
+    size: (u32, u32),   // Size of the area, it can be larger than the visible part
+    clip: Rect,         // Visible part of the area on the canvas
+}
+
+impl Area<'_> {
+    pub fn width(&self) -> u32 {
+        self.size.0
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size.1
+    }
+
+    /* Put a single character, it is dropped outside of the visible part */
+    pub fn put(&mut self, x: u32, y: u32, c: char) {
+        if x >= self.size.0 || y >= self.size.1 {
+            return;
+        }
+
+        let (x, y) = (
+            self.origin.0.saturating_add(x),
+            self.origin.1.saturating_add(y),
+        );
+        if x >= self.clip.x && x < self.clip.right() && y >= self.clip.y && y < self.clip.bottom() {
+            let index = self.canvas.index(x, y);
+            self.canvas.cells[index] = c;
+        }
+    }
+
+    /* Write the text on a single row, from `x` to the right */
+    pub fn text(&mut self, x: u32, y: u32, text: &str) {
+        for (offset, c) in text.chars().enumerate() {
+            let offset = u32::try_from(offset).unwrap_or(u32::MAX);
+            self.put(x.saturating_add(offset), y, c);
+        }
+    }
+
+    /* Draw a box around the area with Unicode box drawing characters */
+    pub fn border(&mut self) {
+        let (width, height) = self.size;
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (right, bottom) = (width - 1, height - 1);
+        let visible = self.visible();
+
+        // Only visible cells are walked, so huge areas are cheap
+        for x in visible.x.max(1)..visible.right().min(right) {
+            self.put(x, 0, '─');
+            self.put(x, bottom, '─');
+        }
+        for y in visible.y.max(1)..visible.bottom().min(bottom) {
+            self.put(0, y, '│');
+            self.put(right, y, '│');
+        }
+        self.put(0, 0, '┌');
+        self.put(right, 0, '┐');
+        self.put(0, bottom, '└');
+        self.put(right, bottom, '┘');
+    }
+
+    /* Visible part of the area, relative to its top left corner */
+    fn visible(&self) -> Rect {
+        Rect::new(
+            self.clip.x.saturating_sub(self.origin.0),
+            self.clip.y.saturating_sub(self.origin.1),
+            self.clip.width,
+            self.clip.height,
+        )
+    }
+
+    /* Nested area, relative to this one, it is clipped by this one too */
+    pub fn area(&mut self, rect: Rect) -> Area<'_> {
+        let absolute = Rect::new(
+            self.origin.0.saturating_add(rect.x),
+            self.origin.1.saturating_add(rect.y),
+            rect.width,
+            rect.height,
+        );
+        let parent = Rect::new(self.origin.0, self.origin.1, self.size.0, self.size.1);
+
+        Area {
+            canvas: self.canvas,
+            origin: (absolute.x, absolute.y),
+            size: (rect.width, rect.height),
+            clip: self.clip.intersect(parent).intersect(absolute),
+        }
+    }
+
+    /* Area inside of the border */
+    pub fn inner(&mut self) -> Area<'_> {
+        let width = self.size.0.saturating_sub(2);
+        let height = self.size.1.saturating_sub(2);
+        self.area(Rect::new(1, 1, width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersection() {
+        let rect = Rect::new(2, 2, 4, 4);
+
+        assert_eq!(
+            Rect::new(4, 3, 2, 3),
+            rect.intersect(Rect::new(4, 3, 10, 10))
+        );
+        assert_eq!(0, rect.intersect(Rect::new(10, 0, 5, 5)).width);
+    }
+
+    #[test]
+    fn clipping() {
+        let mut canvas = Canvas::new(8, 3);
+
+        let mut area = canvas.area(Rect::new(5, 1, 6, 3));
+        area.border(); // Right side is outside of the canvas
+        area.inner().text(0, 0, "clipped"); // Longer than the inner area
+        canvas.area(Rect::new(0, 0, 3, 1)).text(1, 0, "abc"); // Longer than the area
+
+        assert_eq!(" ab\n     ┌──\n     │cl\n", canvas.to_string());
+        assert_eq!(Some('c'), canvas.get(6, 2));
+        assert_eq!(None, canvas.get(8, 0));
+    }
+
+    #[test]
+    fn large_positions_are_clipped() {
+        let mut canvas = Canvas::new(4, 2);
+
+        let mut area = canvas.area(Rect::new(u32::MAX - 1, 0, u32::MAX, u32::MAX));
+        area.border();
+        area.area(Rect::new(u32::MAX, u32::MAX, 2, 2))
+            .put(0, 0, 'x');
+        canvas
+            .area(Rect::new(0, 1, u32::MAX, 1))
+            .text(u32::MAX - 1, 0, "text");
+
+        assert_eq!("\n\n", canvas.to_string());
+        assert_eq!(u32::MAX, Rect::new(u32::MAX, 0, 10, 0).right());
+    }
+
+    #[test]
+    fn index_doesnt_overflow_u32() {
+        // 65536 * 65536 is one more than `u32::MAX`, the canvas itself stays small
+        let canvas = Canvas::new(65_536, 0);
+        assert_eq!(u32::MAX as usize + 1, canvas.index(0, 65_536));
+        assert_eq!(None, canvas.get(1, 65_536));
+    }
+}
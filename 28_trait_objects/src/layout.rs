@@ -0,0 +1,467 @@
+// Layout of components: containers measure their children and arrange them into rectangles.
+
+use crate::canvas::{Area, Rect};
+use crate::Draw;
+
+/*
+  Result of the arrange pass: the rectangle of a component on the screen
+  and rectangles of its children, nested as deep as the containers are
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub rect: Rect,
+    pub children: Vec<Layout>,
+}
+
+impl Layout {
+    /* Layout of a component without children */
+    pub fn leaf(rect: Rect) -> Layout {
+        Layout {
+            rect,
+            children: Vec::new(),
+        }
+    }
+}
+
+/*
+  Components one under another (or one after another for horizontal stack),
+  with `spacing` empty cells between them, every child keeps its own size
+*/
+pub struct VStack {
+    pub spacing: u32,
+    pub children: Vec<Box<dyn Draw>>,
+}
+
+pub struct HStack {
+    pub spacing: u32,
+    pub children: Vec<Box<dyn Draw>>,
+}
+
+/* Components in rows of `columns` cells, column is as wide as its widest child, row as tall as its tallest */
+pub struct Grid {
+    pub columns: usize,
+    pub spacing: u32, // Empty cells between columns and between rows
+    pub children: Vec<Box<dyn Draw>>,
+}
+
+/* Empty cells around a single component */
+pub struct Padding {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub child: Box<dyn Draw>,
+}
+
+impl Padding {
+    /* The same padding on every side */
+    pub fn uniform(padding: u32, child: Box<dyn Draw>) -> Padding {
+        Padding {
+            top: padding,
+            right: padding,
+            bottom: padding,
+            left: padding,
+            child,
+        }
+    }
+}
+
+impl Draw for VStack {
+    fn size(&self) -> (u32, u32) {
+        stack_size(&self.children, self.spacing, true)
+    }
+
+    fn arrange(&self, rect: Rect) -> Layout {
+        Layout {
+            rect,
+            children: stack(&self.children, self.spacing, rect, true),
+        }
+    }
+
+    fn draw(&self, area: &mut Area) {
+        draw_arranged(self, area);
+    }
+
+    fn draw_layout(&self, layout: &Layout, area: &mut Area) {
+        draw_children(&self.children, layout, area);
+    }
+}
+
+impl Draw for HStack {
+    fn size(&self) -> (u32, u32) {
+        stack_size(&self.children, self.spacing, false)
+    }
+
+    fn arrange(&self, rect: Rect) -> Layout {
+        Layout {
+            rect,
+            children: stack(&self.children, self.spacing, rect, false),
+        }
+    }
+
+    fn draw(&self, area: &mut Area) {
+        draw_arranged(self, area);
+    }
+
+    fn draw_layout(&self, layout: &Layout, area: &mut Area) {
+        draw_children(&self.children, layout, area);
+    }
+}
+
+impl Grid {
+    /* Widths of columns and heights of rows */
+    fn tracks(&self) -> (Vec<u32>, Vec<u32>) {
+        let columns = self.columns.max(1);
+        let mut widths = vec![0; columns.min(self.children.len())];
+        let mut heights = vec![0; self.children.len().div_ceil(columns)];
+
+        for (index, child) in self.children.iter().enumerate() {
+            let (width, height) = child.size();
+            let (row, column) = (index / columns, index % columns);
+            widths[column] = widths[column].max(width);
+            heights[row] = heights[row].max(height);
+        }
+
+        (widths, heights)
+    }
+}
+
+impl Draw for Grid {
+    fn size(&self) -> (u32, u32) {
+        let (widths, heights) = self.tracks();
+        (
+            track_length(&widths, self.spacing),
+            track_length(&heights, self.spacing),
+        )
+    }
+
+    fn arrange(&self, rect: Rect) -> Layout {
+        let (widths, heights) = self.tracks();
+        let offsets = |tracks: &[u32]| -> Vec<u32> {
+            let mut offset = 0u32;
+            tracks
+                .iter()
+                .map(|track| {
+                    let start = offset;
+                    offset = offset.saturating_add(*track).saturating_add(self.spacing);
+                    start
+                })
+                .collect()
+        };
+        let (xs, ys) = (offsets(&widths), offsets(&heights));
+        let columns = self.columns.max(1);
+
+        let children = self
+            .children
+            .iter()
+            .enumerate()
+            .map(|(index, child)| {
+                let (width, height) = child.size();
+                let x = rect.x.saturating_add(xs[index % columns]);
+                let y = rect.y.saturating_add(ys[index / columns]);
+                child.arrange(Rect::new(x, y, width, height))
+            })
+            .collect();
+
+        Layout { rect, children }
+    }
+
+    fn draw(&self, area: &mut Area) {
+        draw_arranged(self, area);
+    }
+
+    fn draw_layout(&self, layout: &Layout, area: &mut Area) {
+        draw_children(&self.children, layout, area);
+    }
+}
+
+impl Draw for Padding {
+    fn size(&self) -> (u32, u32) {
+        let (width, height) = self.child.size();
+        (
+            self.left.saturating_add(width).saturating_add(self.right),
+            self.top.saturating_add(height).saturating_add(self.bottom),
+        )
+    }
+
+    fn arrange(&self, rect: Rect) -> Layout {
+        let (width, height) = self.child.size();
+        let (x, y) = (
+            rect.x.saturating_add(self.left),
+            rect.y.saturating_add(self.top),
+        );
+        let inner = Rect::new(x, y, width, height);
+
+        Layout {
+            rect,
+            children: vec![self.child.arrange(inner)],
+        }
+    }
+
+    fn draw(&self, area: &mut Area) {
+        draw_arranged(self, area);
+    }
+
+    fn draw_layout(&self, layout: &Layout, area: &mut Area) {
+        draw_children(std::slice::from_ref(&self.child), layout, area);
+    }
+}
+
+/*
+  Measure pass of a stack: lengths of children along the axis are added up,
+  and the stack is as wide (tall) as its widest (tallest) child
+*/
+fn stack_size(children: &[Box<dyn Draw>], spacing: u32, vertical: bool) -> (u32, u32) {
+    let sizes: Vec<(u32, u32)> = children.iter().map(|child| child.size()).collect();
+    let lengths: Vec<u32> = sizes
+        .iter()
+        .map(|&(width, height)| if vertical { height } else { width })
+        .collect();
+    let across = sizes
+        .iter()
+        .map(|&(width, height)| if vertical { width } else { height })
+        .max()
+        .unwrap_or(0);
+    let along = track_length(&lengths, spacing);
+
+    if vertical {
+        (across, along)
+    } else {
+        (along, across)
+    }
+}
+
+// Arrange pass of a stack: children follow each other from the corner of the rectangle.
+pub(crate) fn stack(
+    children: &[Box<dyn Draw>],
+    spacing: u32,
+    rect: Rect,
+    vertical: bool,
+) -> Vec<Layout> {
+    let mut offset = 0u32;
+
+    children
+        .iter()
+        .map(|child| {
+            let (width, height) = child.size();
+            let child_rect = if vertical {
+                Rect::new(rect.x, rect.y.saturating_add(offset), width, height)
+            } else {
+                Rect::new(rect.x.saturating_add(offset), rect.y, width, height)
+            };
+            let length = if vertical { height } else { width };
+            offset = offset.saturating_add(length).saturating_add(spacing);
+            child.arrange(child_rect)
+        })
+        .collect()
+}
+
+// Length of tracks one after another, with spacing between them, it stops at `u32::MAX` like the canvas.
+fn track_length(tracks: &[u32], spacing: u32) -> u32 {
+    let gaps = u32::try_from(tracks.len().saturating_sub(1)).unwrap_or(u32::MAX);
+    let tracks = tracks.iter().copied().fold(0, u32::saturating_add);
+    tracks.saturating_add(gaps.saturating_mul(spacing))
+}
+
+// Draw the container on its own (not from the screen): arrange it inside of the area first.
+fn draw_arranged(container: &dyn Draw, area: &mut Area) {
+    let layout = container.arrange(Rect::new(0, 0, area.width(), area.height()));
+    container.draw_layout(&layout, area);
+}
+
+// Draw children into their rectangles of the layout, rectangles are moved relative to the area of the container.
+fn draw_children(children: &[Box<dyn Draw>], layout: &Layout, area: &mut Area) {
+    for (child, child_layout) in children.iter().zip(&layout.children) {
+        let rect = Rect::new(
+            child_layout.rect.x.saturating_sub(layout.rect.x),
+            child_layout.rect.y.saturating_sub(layout.rect.y),
+            child_layout.rect.width,
+            child_layout.rect.height,
+        );
+        child.draw_layout(child_layout, &mut area.area(rect));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Canvas;
+
+    // Component of a fixed size, which fills its area with a character
+    struct Block(u32, u32, char);
+
+    impl Draw for Block {
+        fn size(&self) -> (u32, u32) {
+            (self.0, self.1)
+        }
+
+        fn draw(&self, area: &mut Area) {
+            for y in 0..area.height() {
+                for x in 0..area.width() {
+                    area.put(x, y, self.2);
+                }
+            }
+        }
+    }
+
+    fn block(width: u32, height: u32, c: char) -> Box<dyn Draw> {
+        Box::new(Block(width, height, c))
+    }
+
+    fn rects(layout: &Layout) -> Vec<Rect> {
+        layout.children.iter().map(|child| child.rect).collect()
+    }
+
+    #[test]
+    fn stacks() {
+        let vstack = VStack {
+            spacing: 1,
+            children: vec![block(3, 2, 'a'), block(5, 1, 'b')],
+        };
+        assert_eq!((5, 4), vstack.size());
+        assert_eq!(
+            vec![Rect::new(2, 1, 3, 2), Rect::new(2, 4, 5, 1)],
+            rects(&vstack.arrange(Rect::new(2, 1, 10, 10)))
+        );
+
+        let hstack = HStack {
+            spacing: 2,
+            children: vec![block(3, 2, 'a'), block(5, 1, 'b')],
+        };
+        assert_eq!((10, 2), hstack.size());
+        assert_eq!(
+            vec![Rect::new(0, 0, 3, 2), Rect::new(5, 0, 5, 1)],
+            rects(&hstack.arrange(Rect::new(0, 0, 10, 2)))
+        );
+        assert_eq!(
+            (0, 0),
+            VStack {
+                spacing: 1,
+                children: vec![]
+            }
+            .size()
+        );
+    }
+
+    #[test]
+    fn grid() {
+        let grid = Grid {
+            columns: 2,
+            spacing: 1,
+            children: vec![
+                block(2, 1, 'a'),
+                block(1, 3, 'b'),
+                block(4, 1, 'c'), // Last row has a single cell
+            ],
+        };
+
+        assert_eq!((6, 5), grid.size());
+        assert_eq!(
+            vec![
+                Rect::new(1, 1, 2, 1),
+                Rect::new(6, 1, 1, 3),
+                Rect::new(1, 5, 4, 1)
+            ],
+            rects(&grid.arrange(Rect::new(1, 1, 6, 5)))
+        );
+    }
+
+    #[test]
+    fn nested_layout() {
+        let root = VStack {
+            spacing: 0,
+            children: vec![
+                Box::new(Padding {
+                    top: 1,
+                    right: 0,
+                    bottom: 0,
+                    left: 2,
+                    child: Box::new(HStack {
+                        spacing: 1,
+                        children: vec![block(1, 1, 'a'), block(2, 2, 'b')],
+                    }),
+                }),
+                Box::new(Padding::uniform(
+                    1,
+                    Box::new(Grid {
+                        columns: 1,
+                        spacing: 0,
+                        children: vec![block(3, 1, 'c'), block(1, 1, 'd')],
+                    }),
+                )),
+            ],
+        };
+        assert_eq!((6, 7), root.size());
+
+        let layout = root.arrange(Rect::new(0, 0, 6, 7));
+        assert_eq!(
+            vec![Rect::new(0, 0, 6, 3), Rect::new(0, 3, 5, 4)],
+            rects(&layout)
+        );
+
+        let hstack = &layout.children[0].children[0];
+        assert_eq!(Rect::new(2, 1, 4, 2), hstack.rect);
+        assert_eq!(
+            vec![Rect::new(2, 1, 1, 1), Rect::new(4, 1, 2, 2)],
+            rects(hstack)
+        );
+
+        let grid = &layout.children[1].children[0];
+        assert_eq!(
+            vec![Rect::new(1, 4, 3, 1), Rect::new(1, 5, 1, 1)],
+            rects(grid)
+        );
+
+        // Drawing follows the layout
+        let mut canvas = Canvas::new(6, 7);
+        root.draw(&mut canvas.area(Rect::new(0, 0, 6, 7)));
+        assert_eq!("\n  a bb\n    bb\n\n ccc\n d\n\n", canvas.to_string());
+
+        // Rectangles of the given layout are drawn, nothing is arranged again
+        let mut moved = layout.clone();
+        moved.children[1].children[0].children[1].rect = Rect::new(3, 5, 1, 1);
+        let mut canvas = Canvas::new(6, 7);
+        root.draw_layout(&moved, &mut canvas.area(moved.rect));
+        assert_eq!("\n  a bb\n    bb\n\n ccc\n   d\n\n", canvas.to_string());
+    }
+
+    #[test]
+    fn huge_sizes_saturate() {
+        let max = u32::MAX;
+        let vstack = VStack {
+            spacing: max,
+            children: vec![block(max, max, 'a'), block(1, max, 'b'), block(1, 1, 'c')],
+        };
+        assert_eq!((max, max), vstack.size());
+        assert_eq!(
+            vec![
+                Rect::new(1, 1, max, max),
+                Rect::new(1, max, 1, max),
+                Rect::new(1, max, 1, 1)
+            ],
+            rects(&vstack.arrange(Rect::new(1, 1, 10, 10)))
+        );
+
+        let grid = Grid {
+            columns: 2,
+            spacing: max,
+            children: vec![block(max, 1, 'a'), block(1, max, 'b'), block(1, 1, 'c')],
+        };
+        assert_eq!((max, max), grid.size());
+        assert_eq!(
+            vec![
+                Rect::new(0, 0, max, 1),
+                Rect::new(max, 0, 1, max),
+                Rect::new(0, max, 1, 1)
+            ],
+            rects(&grid.arrange(Rect::new(0, 0, 10, 10)))
+        );
+
+        let padding = Padding::uniform(max, block(max, max, 'a'));
+        assert_eq!((max, max), padding.size());
+        assert_eq!(
+            vec![Rect::new(max, max, max, max)],
+            rects(&padding.arrange(Rect::new(1, 1, 1, 1)))
+        );
+    }
+}
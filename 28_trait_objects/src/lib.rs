@@ -1,23 +1,63 @@
 // Library crate
 
-#[allow(dead_code)]
-#[allow(unused_variables)]
+use canvas::{Area, Canvas, Rect};
+use layout::Layout;
+
+// Character-cell canvas, which components draw into
+pub mod canvas;
+// Containers, which arrange their children: stacks, grid and padding
+pub mod layout;
 
 /* Trait for common behaviour */
 pub trait Draw {
-    fn draw(&self);
+    fn size(&self) -> (u32, u32); // Measure pass: width and height in cells, containers measure children
+    fn draw(&self, area: &mut Area); // Area has the size of the component, it may be clipped
+
+    // Arrange pass: place the component (and its children) into the rectangle
+    fn arrange(&self, rect: Rect) -> Layout {
+        Layout::leaf(rect)
+    }
+
+    // Draw the arranged component, containers pass rectangles of the layout to their children
+    fn draw_layout(&self, _layout: &Layout, area: &mut Area) {
+        self.draw(area);
+    }
 }
 
 pub struct Screen {
+    pub width: u32,
+    pub height: u32,
     pub components: Vec<Box<dyn Draw>>, // Box smart pointer, fix any data which implmenet Draw trait
 }
 
 impl Screen {
-    pub fn run(&self) {
-        for component in self.components.iter() {
-            component.draw();
+    /* Components are stacked one under another, like in `VStack` */
+    pub fn layout(&self) -> Layout {
+        let rect = Rect::new(0, 0, self.width, self.height);
+
+        Layout {
+            rect,
+            children: layout::stack(&self.components, 0, rect, true),
         }
     }
+
+    /*
+      Draw components into rectangles of the layout, it is arranged once for the whole tree,
+      parts outside of the screen are clipped
+    */
+    pub fn render(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+
+        for (component, layout) in self.components.iter().zip(&self.layout().children) {
+            component.draw_layout(layout, &mut canvas.area(layout.rect));
+        }
+
+        canvas
+    }
+
+    pub fn run(&self) {
+        print!("{}", self.render());
+    }
 }
 
 /*
@@ -35,8 +75,64 @@ pub struct Button {
 }
 
 impl Draw for Button {
-    fn draw(&self) {
-        // draw button
-        println!("Draw button!");
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn draw(&self, area: &mut Area) {
+        // draw border, when there is a room for it, and the label in the middle
+        let mut inner = if self.height >= 3 {
+            area.border();
+            area.inner()
+        } else {
+            area.area(Rect::new(0, 0, self.width, self.height))
+        };
+
+        let label = self.label.chars().count() as u32;
+        let x = inner.width().saturating_sub(label) / 2;
+        let y = inner.height().saturating_sub(1) / 2;
+        inner.text(x, y, &self.label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn button(width: u32, height: u32, label: &str) -> Box<Button> {
+        Box::new(Button {
+            width,
+            height,
+            label: String::from(label),
+        })
+    }
+
+    #[test]
+    fn buttons_snapshot() {
+        let screen = Screen {
+            width: 14,
+            height: 9,
+            components: vec![
+                button(12, 3, "Click me"),
+                button(6, 1, "OK"),
+                button(9, 3, "Cancel now"), // Label is clipped by the border
+                button(20, 3, "Wide"),      // Right side and bottom are clipped by the screen
+            ],
+        };
+
+        assert_eq!(
+            "\
+┌──────────┐
+│ Click me │
+└──────────┘
+  OK
+┌───────┐
+│Cancel │
+└───────┘
+┌─────────────
+│       Wide
+",
+            screen.render().to_string()
+        );
     }
 }
@@ -29,6 +29,8 @@
 
 #[allow(dead_code)]
 #[allow(unused_variables)]
+use trait_objects::canvas::Area;
+use trait_objects::layout::{HStack, Padding, VStack};
 use trait_objects::{Button, Draw, Screen};
 
 struct SelectBox {
@@ -38,20 +40,34 @@ struct SelectBox {
 }
 
 impl Draw for SelectBox {
-    fn draw(&self) {
-        // draw selectbox
-        println!("Draw selectbox!");
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn draw(&self, area: &mut Area) {
+        // draw border and options inside of it, first option is selected
+        area.border();
+        let mut inner = area.inner();
+
+        for (row, option) in self.options.iter().enumerate() {
+            let marker = if row == 0 { "(*) " } else { "( ) " };
+            inner.text(0, row as u32, &format!("{}{}", marker, option));
+        }
     }
 }
 
 fn main() {
     /* Use our `GUI` library */
-    let mut screen = Screen { components: vec![] };
+    let mut screen = Screen {
+        width: 40,
+        height: 10,
+        components: vec![],
+    };
 
     /* Add components */
     let select_box = Box::new(SelectBox {
-        width: 100,
-        height: 100,
+        width: 14,
+        height: 5,
         options: vec![
             String::from("Yes"),
             String::from("No"),
@@ -60,14 +76,63 @@ fn main() {
     });
 
     let button = Box::new(Button {
-        width: 100,
-        height: 30,
+        width: 14,
+        height: 3,
         label: String::from("Click me"),
     });
 
-    screen.components.push(select_box);
-    screen.components.push(button);
+    let cancel = Box::new(Button {
+        width: 14,
+        height: 3,
+        label: String::from("Cancel"),
+    });
+
+    /* Containers place components: buttons go under each other, next to the select box */
+    let buttons = Box::new(VStack {
+        spacing: 1,
+        children: vec![button, cancel],
+    });
+
+    screen.components.push(Box::new(Padding::uniform(
+        1,
+        Box::new(HStack {
+            spacing: 2,
+            children: vec![select_box, buttons],
+        }),
+    )));
 
     /* Run our screen renderer */
     screen.run();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_box_snapshot() {
+        let screen = Screen {
+            width: 12,
+            height: 4,
+            components: vec![Box::new(SelectBox {
+                width: 11,
+                height: 5,
+                options: vec![
+                    String::from("Yes"),
+                    String::from("Not now"), // Clipped by the border
+                    String::from("Later"),   // Clipped by the screen
+                ],
+            })],
+        };
+
+        assert_eq!(
+            "\
+┌─────────┐
+│(*) Yes  │
+│( ) Not n│
+│( ) Later│
+",
+            screen.render().to_string()
+        );
+    }
+}
@@ -0,0 +1,78 @@
+// Audit log of a Post: every transition attempt is recorded, accepted or not.
+
+use std::error::Error;
+use std::fmt;
+use std::time::SystemTime;
+
+// Define transitions, which can be requested for a Post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    RequestReview,
+    Reject,
+    Approve,
+    Publish,
+    Archive,
+}
+
+impl Action {
+    // Get the name of the action, it is saved with the Post.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::RequestReview => "request_review",
+            Action::Reject => "reject",
+            Action::Approve => "approve",
+            Action::Publish => "publish",
+            Action::Archive => "archive",
+        }
+    }
+
+    // Find the action by its saved name.
+    pub fn from_name(name: &str) -> Option<Action> {
+        [
+            Action::RequestReview,
+            Action::Reject,
+            Action::Approve,
+            Action::Publish,
+            Action::Archive,
+        ]
+        .into_iter()
+        .find(|action| action.name() == name)
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Define a single record of the log: who tried what, when, and how it ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub timestamp: SystemTime,
+    pub actor: String,
+    pub action: Action,
+    pub from: &'static str,
+    pub to: &'static str,
+    pub accepted: bool,
+}
+
+// Define an error of a strict Post: the transition isn't allowed in the current state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionError {
+    pub action: Action,
+    pub state: &'static str,
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` isn't allowed in state {}",
+            self.action.name(),
+            self.state
+        )
+    }
+}
+
+impl Error for TransitionError {}
@@ -1,101 +1,741 @@
 // Library crate
 
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+use audit::{Action, AuditEntry, TransitionError};
+use clock::{Clock, SystemClock};
+use machine::{StateMachine, Transition};
+use revision::{DiffLine, EditError, Revision};
+
+// Audit log of transition attempts.
+pub mod audit;
+// Clocks, which tell the time of transitions and edits.
+pub mod clock;
+// Generic state machine, which drives the Post (and can drive other workflows).
+pub mod machine;
+// Revisions of the content, and diff between them.
+pub mod revision;
+// File-backed store, which saves and loads posts with their states.
+pub mod store;
+// Type-state variant of the same Post, where invalid transitions don't compile.
+pub mod typed;
+
+// Amount of distinct approvals, which a post needs to be published.
+const REQUIRED_APPROVALS: usize = 2;
+// Actor of transitions, which happen on time.
+const CLOCK_ACTOR: &str = "clock";
+
 // Define a structure representing a Post, which can have different states and content.
 pub struct Post {
-    state: Option<Box<dyn State>>, // Store the current state of the Post.
-    content: String,               // Store the content of the Post.
+    // Store the current state of the Post.
+    state: PostState,
+    // Store every edit of the content.
+    revisions: Vec<Revision>,
+    // Store number of the approved revision, its content is the content of the published Post.
+    published: Option<usize>,
+    // Store approvers of the current review.
+    approvals: Vec<String>,
+    // Store names of states the Post has been in.
+    history: Vec<String>,
+    // Store every transition attempt.
+    audit_log: Vec<AuditEntry>,
+    // Return errors for transitions, which aren't allowed.
+    strict: bool,
+    // Store time, when approved Post is published.
+    publish_at: Option<SystemTime>,
+    // Store time, when published Post is archived.
+    expires_at: Option<SystemTime>,
+    // Tell the time of edits and transitions.
+    clock: Arc<dyn Clock>,
 }
 
 impl Post {
     // Create a new Post with initial state set to Draft and empty content.
     pub fn new() -> Post {
+        Post::with_clock(Arc::new(SystemClock))
+    }
+
+    // Create a new Post, which takes the time from the clock.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Post {
+        let state = machine().initial();
+
         Post {
-            state: Some(Box::new(Draft {})), // Initial state set to Draft.
-            content: String::new(),          // Empty content initially.
+            state,                            // Initial state set to Draft.
+            revisions: Vec::new(),            // Empty content initially.
+            published: None,                  // Nothing is approved yet.
+            approvals: Vec::new(),            // No approvals initially.
+            history: vec![state.to_string()], // History starts with Draft.
+            audit_log: Vec::new(),            // Nothing is attempted yet.
+            strict: false,                    // Transitions, which aren't allowed, are ignored.
+            publish_at: None,                 // Published right after approval.
+            expires_at: None,                 // Never archived.
+            clock,
         }
     }
 
-    // Add text to the content of the Post.
-    pub fn add_text(&mut self, text: &str) {
-        self.content.push_str(text);
+    // Add text to the content of the Post, only Draft can be edited, every edit is a new revision.
+    pub fn add_text(&mut self, text: &str) -> Result<(), EditError> {
+        let content = format!("{}{}", self.text(), text);
+        self.edit(content)
     }
 
-    // Get the content of the Post.
+    // Get the content of the Post, only published Post has it, and it is the approved revision.
     pub fn content(&self) -> &str {
-        self.state.as_ref().unwrap().content(self) // Get content based on the current state.
+        match (self.state, self.published) {
+            (PostState::Published, Some(number)) => self.revision(number).unwrap_or(""),
+            _ => "",
+        }
+    }
+
+    // Get the latest text of the Post, even when it isn't published.
+    pub fn text(&self) -> &str {
+        self.revisions
+            .last()
+            .map_or("", |revision| revision.content.as_str())
+    }
+
+    // Get every revision of the content, the oldest first.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    // Get the diff between two revisions, revision 0 is the empty Post before the first edit.
+    pub fn diff(&self, from: usize, to: usize) -> Result<Vec<DiffLine>, EditError> {
+        Ok(revision::diff(self.revision(from)?, self.revision(to)?))
+    }
+
+    // Make a new revision with the content of an earlier one, only Draft can be reverted.
+    pub fn revert(&mut self, number: usize) -> Result<(), EditError> {
+        let content = self.revision(number)?.to_string();
+        self.edit(content)
+    }
+
+    // Publish the approved Post at the time, only Draft can be scheduled.
+    pub fn schedule(&mut self, publish_at: SystemTime) -> Result<(), EditError> {
+        self.check_draft()?;
+        self.publish_at = Some(publish_at);
+        Ok(())
+    }
+
+    // Archive the published Post at the time, only Draft can get the expiry.
+    pub fn set_expiry(&mut self, expires_at: SystemTime) -> Result<(), EditError> {
+        self.check_draft()?;
+        self.expires_at = Some(expires_at);
+        Ok(())
+    }
+
+    /*
+        Move the Post on time: scheduled Post is published, and expired one
+        is archived, returns `true` when the state changed.
+        Only taken transitions are recorded, so ticks don't fill the log.
+    */
+    pub fn tick(&mut self, now: SystemTime) -> bool {
+        let from = self.state;
+
+        for action in [Action::Publish, Action::Archive] {
+            let _ = self.transition_at(CLOCK_ACTOR, action, now, false);
+        }
+
+        self.state != from
+    }
+
+    // Get the content of the revision by its number.
+    fn revision(&self, number: usize) -> Result<&str, EditError> {
+        match number {
+            0 => Ok(""),
+            number => self
+                .revisions
+                .get(number - 1)
+                .map(|revision| revision.content.as_str())
+                .ok_or(EditError::UnknownRevision(number)),
+        }
+    }
+
+    // Replace the content with a new revision.
+    fn edit(&mut self, content: String) -> Result<(), EditError> {
+        self.check_draft()?;
+
+        self.revisions.push(Revision {
+            number: self.revisions.len() + 1,
+            timestamp: self.clock.now(),
+            content,
+        });
+        Ok(())
+    }
+
+    // Return errors for transitions, which aren't allowed, instead of ignoring them.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
     }
 
     // Transition the Post to the PendingReview state.
-    pub fn request_review(&mut self) {
-        if let Some(state) = self.state.take() {
-            self.state = Some(state.request_review());
+    pub fn request_review(&mut self, actor: &str) -> Result<(), TransitionError> {
+        self.transition(actor, Action::RequestReview)
+    }
+
+    // Transition the Post back to the Draft state, when review found problems.
+    pub fn reject(&mut self, actor: &str) -> Result<(), TransitionError> {
+        self.transition(actor, Action::Reject)
+    }
+
+    // Approve the Post on behalf of the approver, it is published after enough distinct approvals.
+    pub fn approve(&mut self, approver: &str) -> Result<(), TransitionError> {
+        self.transition(approver, Action::Approve)
+    }
+
+    // Get the approvers of the Post (in order of approval).
+    pub fn approvals(&self) -> &[String] {
+        &self.approvals
+    }
+
+    // Get the name of the current state.
+    pub fn state(&self) -> &'static str {
+        self.state.name()
+    }
+
+    // Get names of states the Post has been in, the current one is the last.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    // Get every transition attempt, the oldest first.
+    pub fn audit_log(&self) -> &[AuditEntry] {
+        &self.audit_log
+    }
+
+    // Get the transition table of every Post as a Graphviz DOT graph.
+    pub fn state_diagram() -> String {
+        machine().to_dot("Post")
+    }
+
+    // Only Draft can be changed.
+    fn check_draft(&self) -> Result<(), EditError> {
+        if self.state != PostState::Draft {
+            return Err(EditError::NotDraft {
+                state: self.state.name(),
+            });
         }
+        Ok(())
+    }
+
+    // Request the transition now, by the clock of the Post.
+    fn transition(&mut self, actor: &str, action: Action) -> Result<(), TransitionError> {
+        let now = self.clock.now();
+        self.transition_at(actor, action, now, true)
     }
 
-    // Transition the Post to the Published state.
-    pub fn approve(&mut self) {
-        if let Some(state) = self.state.take() {
-            self.state = Some(state.approve());
+    /*
+        Fire the event on the state machine, record the attempt
+        and the change, refused transition is an error only for strict Post.
+    */
+    fn transition_at(
+        &mut self,
+        actor: &str,
+        action: Action,
+        now: SystemTime,
+        record_refused: bool,
+    ) -> Result<(), TransitionError> {
+        let from = self.state;
+        let mut review = Review {
+            actor: actor.to_string(),
+            approvals: mem::take(&mut self.approvals),
+            revision: self.revisions.len(),
+            published: self.published,
+            now,
+            publish_at: self.publish_at,
+            expires_at: self.expires_at,
+        };
+
+        let to = machine().fire(from, action, &mut review);
+        let accepted = to.is_some();
+        let to = to.unwrap_or(from);
+
+        self.state = to;
+        self.approvals = review.approvals;
+        self.published = review.published;
+
+        if !accepted && !record_refused {
+            return Ok(());
+        }
+        self.audit_log.push(AuditEntry {
+            timestamp: now,
+            actor: actor.to_string(),
+            action,
+            from: from.name(),
+            to: to.name(),
+            accepted,
+        });
+        if to != from {
+            self.history.push(to.to_string());
         }
+
+        if !accepted && self.strict {
+            return Err(TransitionError {
+                action,
+                state: from.name(),
+            });
+        }
+        Ok(())
     }
 }
 
-// Define a trait representing the state of a Post.
-trait State {
-    fn request_review(self: Box<Self>) -> Box<dyn State>; // Request review transition.
-    fn approve(self: Box<Self>) -> Box<dyn State>; // Approve transition.
-    fn content<'a>(&self, _: &'a Post) -> &'a str {
-        // Get content (default implementation).
-        ""
+impl Default for Post {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-// Define the Draft state.
-struct Draft {}
+// Define states of a Post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PostState {
+    Draft,
+    PendingReview,
+    Scheduled,
+    Published,
+    Archived,
+}
+
+impl PostState {
+    // Get the name of the state, it is saved with the Post.
+    fn name(&self) -> &'static str {
+        match self {
+            PostState::Draft => "Draft",
+            PostState::PendingReview => "PendingReview",
+            PostState::Scheduled => "Scheduled",
+            PostState::Published => "Published",
+            PostState::Archived => "Archived",
+        }
+    }
 
-impl State for Draft {
-    // Transition from Draft to PendingReview state.
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        Box::new(PendingReview {})
+    // Find the state by its saved name, unknown name gives `None`.
+    fn from_name(name: &str) -> Option<PostState> {
+        [
+            PostState::Draft,
+            PostState::PendingReview,
+            PostState::Scheduled,
+            PostState::Published,
+            PostState::Archived,
+        ]
+        .into_iter()
+        .find(|state| state.name() == name)
     }
+}
 
-    // No transition from Draft to other states on approval.
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        self
+impl fmt::Display for PostState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
     }
 }
 
-// Define the PendingReview state.
-struct PendingReview {}
+// Define the context of a transition: who requests it and when, approvals, revisions and schedule of the Post.
+struct Review {
+    actor: String,
+    approvals: Vec<String>,
+    revision: usize,
+    published: Option<usize>,
+    now: SystemTime,
+    publish_at: Option<SystemTime>,
+    expires_at: Option<SystemTime>,
+}
+
+impl Review {
+    // The same approver can't approve twice.
+    fn is_new_approver(&self) -> bool {
+        !self.approvals.contains(&self.actor)
+    }
 
-impl State for PendingReview {
-    // No transition from PendingReview to other states on request.
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        self
+    fn is_last_approval(&self) -> bool {
+        self.is_new_approver() && self.approvals.len() + 1 >= REQUIRED_APPROVALS
     }
 
-    // Transition from PendingReview to Published state on approval.
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        Box::new(Published {})
+    fn is_scheduled(&self) -> bool {
+        self.publish_at
+            .is_some_and(|publish_at| publish_at > self.now)
     }
 }
 
-// Define the Published state.
-struct Published {}
+/*
+    Get the state machine of every Post, it is built once:
+    rejection drops approvals of the review, and the Post is published
+    with the last of required distinct approvals (or it waits for the scheduled time),
+    the reviewed revision is kept, and published Post is archived after the expiry.
+*/
+fn machine() -> &'static StateMachine<PostState, Action, Review> {
+    static MACHINE: OnceLock<StateMachine<PostState, Action, Review>> = OnceLock::new();
+
+    MACHINE.get_or_init(|| {
+        use PostState::{Archived, Draft, PendingReview, Published, Scheduled};
+
+        let approve = |review: &mut Review| review.approvals.push(review.actor.clone());
 
-impl State for Published {
-    // No transition from Published to other states on request.
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        self
+        StateMachine::new(Draft)
+            .with(Transition::new(Draft, Action::RequestReview, PendingReview))
+            .with(Transition::new(PendingReview, Action::Reject, Draft))
+            .with(
+                Transition::new(PendingReview, Action::Approve, Scheduled)
+                    .guard("last approval, scheduled", |review: &Review| {
+                        review.is_last_approval() && review.is_scheduled()
+                    })
+                    .action(approve),
+            )
+            .with(
+                Transition::new(PendingReview, Action::Approve, Published)
+                    .guard("last approval", Review::is_last_approval)
+                    .action(approve),
+            )
+            .with(
+                Transition::new(PendingReview, Action::Approve, PendingReview)
+                    .guard("new approver", Review::is_new_approver)
+                    .action(approve),
+            )
+            .with(
+                Transition::new(Scheduled, Action::Publish, Published)
+                    .guard("scheduled time", |review: &Review| !review.is_scheduled()),
+            )
+            .with(Transition::new(Published, Action::Archive, Archived).guard(
+                "expired",
+                |review: &Review| {
+                    review
+                        .expires_at
+                        .is_some_and(|expires_at| expires_at <= review.now)
+                },
+            ))
+            .on_entry(Draft, |review| review.approvals.clear())
+            .on_entry(Published, |review| review.published = Some(review.revision))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock::ManualClock;
+    use std::time::Duration;
+
+    /*
+        Both designs are driven by the same scenarios: type-state Post is wrapped,
+        so transitions, which wouldn't compile for it, leave the wrapper as it is.
+    */
+    trait Blog {
+        fn new() -> Self;
+        fn add_text(&mut self, text: &str);
+        fn request_review(&mut self, actor: &str);
+        fn reject(&mut self, actor: &str);
+        fn approve(&mut self, approver: &str);
+        fn content(&self) -> &str;
+        fn approvals(&self) -> &[String];
+    }
+
+    impl Blog for Post {
+        fn new() -> Self {
+            Post::new()
+        }
+        fn add_text(&mut self, text: &str) {
+            Post::add_text(self, text).unwrap()
+        }
+        fn request_review(&mut self, actor: &str) {
+            Post::request_review(self, actor).unwrap()
+        }
+        fn reject(&mut self, actor: &str) {
+            Post::reject(self, actor).unwrap()
+        }
+        fn approve(&mut self, approver: &str) {
+            Post::approve(self, approver).unwrap()
+        }
+        fn content(&self) -> &str {
+            Post::content(self)
+        }
+        fn approvals(&self) -> &[String] {
+            Post::approvals(self)
+        }
+    }
+
+    enum TypedPost {
+        Draft(typed::DraftPost),
+        PendingReview(typed::PendingReviewPost),
+        Published(typed::Post),
+    }
+
+    impl TypedPost {
+        // Apply the transition, which consumes the post.
+        fn transition(&mut self, change: impl FnOnce(TypedPost) -> TypedPost) {
+            let post = std::mem::replace(self, TypedPost::Draft(typed::Post::new()));
+            *self = change(post);
+        }
+    }
+
+    impl Blog for TypedPost {
+        fn new() -> Self {
+            TypedPost::Draft(typed::Post::new())
+        }
+        fn add_text(&mut self, text: &str) {
+            if let TypedPost::Draft(post) = self {
+                post.add_text(text);
+            }
+        }
+        fn request_review(&mut self, _actor: &str) {
+            self.transition(|post| match post {
+                TypedPost::Draft(post) => TypedPost::PendingReview(post.request_review()),
+                post => post,
+            });
+        }
+        fn reject(&mut self, _actor: &str) {
+            self.transition(|post| match post {
+                TypedPost::PendingReview(post) => TypedPost::Draft(post.reject()),
+                post => post,
+            });
+        }
+        fn approve(&mut self, approver: &str) {
+            self.transition(|post| match post {
+                TypedPost::PendingReview(post) => match post.approve(approver) {
+                    typed::Review::Pending(post) => TypedPost::PendingReview(post),
+                    typed::Review::Published(post) => TypedPost::Published(post),
+                },
+                post => post,
+            });
+        }
+        fn content(&self) -> &str {
+            match self {
+                TypedPost::Published(post) => post.content(),
+                _ => "",
+            }
+        }
+        fn approvals(&self) -> &[String] {
+            match self {
+                TypedPost::PendingReview(post) => post.approvals(),
+                TypedPost::Published(post) => post.approvals(),
+                TypedPost::Draft(_) => &[],
+            }
+        }
+    }
+
+    fn reviewed_post<P: Blog>() -> P {
+        let mut post = P::new();
+        post.add_text("I ate a salad for lunch today!");
+        post.request_review("author");
+        post
+    }
+
+    fn two_distinct_approvals<P: Blog>() {
+        let mut post: P = reviewed_post();
+
+        post.approve("alice");
+        post.approve("alice");
+        assert_eq!("", post.content()); // One approver counts once
+
+        post.approve("bob");
+        assert_eq!("I ate a salad for lunch today!", post.content());
+        assert_eq!(["alice", "bob"], post.approvals());
+    }
+
+    fn reject_returns_to_draft<P: Blog>() {
+        let mut post: P = reviewed_post();
+        post.approve("alice");
+
+        post.reject("alice");
+        assert!(post.approvals().is_empty()); // Approvals are dropped with rejection
+
+        post.approve("bob"); // Draft can't be approved
+        post.request_review("author");
+        post.approve("bob");
+        assert_eq!("", post.content());
+
+        post.approve("carol");
+        assert_eq!("I ate a salad for lunch today!", post.content());
     }
 
-    // No transition from Published to other states on approval.
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        self
+    #[test]
+    fn two_distinct_approvals_of_state_machine() {
+        two_distinct_approvals::<Post>();
+    }
+
+    #[test]
+    fn two_distinct_approvals_of_type_states() {
+        two_distinct_approvals::<TypedPost>();
+    }
+
+    #[test]
+    fn reject_returns_to_draft_of_state_machine() {
+        reject_returns_to_draft::<Post>();
+    }
+
+    #[test]
+    fn reject_returns_to_draft_of_type_states() {
+        reject_returns_to_draft::<TypedPost>();
+    }
+
+    #[test]
+    fn audit_log_records_every_attempt() {
+        let mut post: Post = reviewed_post();
+        post.approve("alice").unwrap();
+        post.approve("alice").unwrap(); // Refused, but ignored
+        post.request_review("author").unwrap();
+
+        let log: Vec<_> = post
+            .audit_log()
+            .iter()
+            .map(|entry| (entry.actor.as_str(), entry.action, entry.to, entry.accepted))
+            .collect();
+        assert_eq!(
+            vec![
+                ("author", Action::RequestReview, "PendingReview", true),
+                ("alice", Action::Approve, "PendingReview", true),
+                ("alice", Action::Approve, "PendingReview", false),
+                ("author", Action::RequestReview, "PendingReview", false),
+            ],
+            log
+        );
+        assert!(post.audit_log()[0].timestamp <= post.audit_log()[3].timestamp);
+    }
+
+    #[test]
+    fn strict_post_returns_errors() {
+        let mut post = Post::new();
+        post.set_strict(true);
+
+        assert_eq!(
+            Err(TransitionError {
+                action: Action::Approve,
+                state: "Draft",
+            }),
+            post.approve("alice")
+        );
+        post.request_review("author").unwrap();
+        post.approve("alice").unwrap();
+        assert_eq!(
+            "`approve` isn't allowed in state PendingReview",
+            post.approve("alice").unwrap_err().to_string()
+        );
+
+        // Refused transition is still in the log, and the state stays the same
+        assert!(!post.audit_log()[3].accepted);
+        assert_eq!("PendingReview", post.state());
     }
 
-    // Get content of the Post (implementation for Published state).
-    fn content<'a>(&self, post: &'a Post) -> &'a str {
-        &post.content
+    #[test]
+    fn state_diagram() {
+        assert_eq!(
+            "digraph Post {
+    rankdir=LR;
+    start [shape=point];
+    \"Draft\" [shape=box];
+    \"PendingReview\" [shape=box];
+    \"Scheduled\" [shape=box];
+    \"Published\" [shape=box];
+    \"Archived\" [shape=box];
+    start -> \"Draft\";
+    \"Draft\" -> \"PendingReview\" [label=\"request_review\"];
+    \"PendingReview\" -> \"Draft\" [label=\"reject\"];
+    \"PendingReview\" -> \"Scheduled\" [label=\"approve [last approval, scheduled]\"];
+    \"PendingReview\" -> \"Published\" [label=\"approve [last approval]\"];
+    \"PendingReview\" -> \"PendingReview\" [label=\"approve [new approver]\"];
+    \"Scheduled\" -> \"Published\" [label=\"publish [scheduled time]\"];
+    \"Published\" -> \"Archived\" [label=\"archive [expired]\"];
+}
+",
+            Post::state_diagram()
+        );
+    }
+
+    #[test]
+    fn revisions_and_editing_rules() {
+        let mut post = Post::new();
+        post.add_text("I ate a salad\n").unwrap();
+        post.add_text("and soup\n").unwrap();
+        post.revert(1).unwrap();
+        post.add_text("and bread\n").unwrap();
+
+        assert_eq!(4, post.revisions().len());
+        assert_eq!(
+            vec![
+                DiffLine::Same(String::from("I ate a salad")),
+                DiffLine::Removed(String::from("and soup")),
+                DiffLine::Added(String::from("and bread")),
+            ],
+            post.diff(2, 4).unwrap()
+        );
+        assert_eq!(Err(EditError::UnknownRevision(5)), post.diff(0, 5));
+
+        post.request_review("author").unwrap();
+        assert_eq!(
+            Err(EditError::NotDraft {
+                state: "PendingReview"
+            }),
+            post.add_text("and cake\n")
+        );
+
+        post.approve("alice").unwrap();
+        post.approve("bob").unwrap();
+        assert_eq!("I ate a salad\nand bread\n", post.content()); // The approved revision
+        assert!(post.revert(2).is_err()); // Published Post can't change
+    }
+
+    #[test]
+    fn scheduled_publishing_and_expiry() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = Arc::new(ManualClock::new(start));
+        let hour = Duration::from_secs(60 * 60);
+
+        let mut post = Post::with_clock(clock.clone());
+        post.add_text("I ate a salad for lunch today!").unwrap();
+        post.schedule(start + hour).unwrap();
+        post.set_expiry(start + 3 * hour).unwrap();
+        post.request_review("author").unwrap();
+        post.approve("alice").unwrap();
+        post.approve("bob").unwrap();
+
+        assert_eq!("Scheduled", post.state());
+        assert_eq!("", post.content());
+        assert!(!post.tick(clock.now())); // Too early
+
+        clock.advance(hour);
+        assert!(post.tick(clock.now()));
+        assert_eq!("I ate a salad for lunch today!", post.content());
+
+        clock.advance(2 * hour);
+        assert!(post.tick(clock.now()));
+        assert_eq!("Archived", post.state());
+        assert_eq!("", post.content());
+
+        // Only taken transitions on time are recorded
+        let log: Vec<_> = post
+            .audit_log()
+            .iter()
+            .filter(|entry| entry.actor == CLOCK_ACTOR)
+            .map(|entry| (entry.action, entry.timestamp))
+            .collect();
+        assert_eq!(
+            vec![
+                (Action::Publish, start + hour),
+                (Action::Archive, start + 3 * hour)
+            ],
+            log
+        );
+        assert_eq!(start, post.revisions()[0].timestamp);
+    }
+
+    #[test]
+    fn late_tick_publishes_and_archives() {
+        let start = SystemTime::UNIX_EPOCH;
+        let mut post = Post::with_clock(Arc::new(ManualClock::new(start)));
+        post.schedule(start + Duration::from_secs(10)).unwrap();
+        post.set_expiry(start + Duration::from_secs(20)).unwrap();
+        post.request_review("author").unwrap();
+        post.approve("alice").unwrap();
+        post.approve("bob").unwrap();
+
+        assert!(post.tick(start + Duration::from_secs(30)));
+        assert_eq!(
+            [
+                "Draft",
+                "PendingReview",
+                "Scheduled",
+                "Published",
+                "Archived"
+            ],
+            post.history()
+        );
     }
 }
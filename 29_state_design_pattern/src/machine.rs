@@ -0,0 +1,288 @@
+// Generic state machine: states change on events, following a table of guarded transitions.
+
+use std::fmt::{self, Write};
+
+// Check of the context, which allows the transition.
+pub type Guard<C> = Box<dyn Fn(&C) -> bool + Send + Sync>;
+// Change of the context on a transition, or on entry to (exit from) a state.
+pub type Hook<C> = Box<dyn Fn(&mut C) + Send + Sync>;
+
+// Define a single row of the transition table.
+pub struct Transition<S, E, C> {
+    from: S,
+    event: E,
+    to: S,
+    guard: Option<(&'static str, Guard<C>)>,
+    action: Option<Hook<C>>,
+}
+
+impl<S, E, C> Transition<S, E, C> {
+    // Create a transition, which is always allowed.
+    pub fn new(from: S, event: E, to: S) -> Self {
+        Transition {
+            from,
+            event,
+            to,
+            guard: None,
+            action: None,
+        }
+    }
+
+    // Allow the transition only when the guard passes, the name is shown in the diagram.
+    pub fn guard(
+        mut self,
+        name: &'static str,
+        guard: impl Fn(&C) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.guard = Some((name, Box::new(guard)));
+        self
+    }
+
+    // Run the action, when the transition is taken (after exit hooks, before entry hooks).
+    pub fn action(mut self, action: impl Fn(&mut C) + Send + Sync + 'static) -> Self {
+        self.action = Some(Box::new(action));
+        self
+    }
+}
+
+/*
+    Define a state machine: the transition table and hooks of states.
+    The machine doesn't keep the current state, so one machine can drive
+    many values, every value keeps its own state and context.
+*/
+pub struct StateMachine<S, E, C> {
+    initial: S,
+    transitions: Vec<Transition<S, E, C>>,
+    entry_hooks: Vec<(S, Hook<C>)>,
+    exit_hooks: Vec<(S, Hook<C>)>,
+}
+
+impl<S: Copy + PartialEq, E: PartialEq, C> StateMachine<S, E, C> {
+    // Create a machine without transitions, values start in the initial state.
+    pub fn new(initial: S) -> Self {
+        StateMachine {
+            initial,
+            transitions: Vec::new(),
+            entry_hooks: Vec::new(),
+            exit_hooks: Vec::new(),
+        }
+    }
+
+    // Add a row to the transition table, rows are tried in order they are added.
+    pub fn with(mut self, transition: Transition<S, E, C>) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    // Run the hook, when a value enters the state.
+    pub fn on_entry(mut self, state: S, hook: impl Fn(&mut C) + Send + Sync + 'static) -> Self {
+        self.entry_hooks.push((state, Box::new(hook)));
+        self
+    }
+
+    // Run the hook, when a value leaves the state.
+    pub fn on_exit(mut self, state: S, hook: impl Fn(&mut C) + Send + Sync + 'static) -> Self {
+        self.exit_hooks.push((state, Box::new(hook)));
+        self
+    }
+
+    // Get the state, which every value starts in.
+    pub fn initial(&self) -> S {
+        self.initial
+    }
+
+    /*
+        Handle the event: the first transition from the state with a passing guard
+        is taken, returns the next state, or `None` when the event isn't allowed.
+        Transition to the same state is internal, it doesn't run entry and exit hooks.
+    */
+    pub fn fire(&self, state: S, event: E, context: &mut C) -> Option<S> {
+        let transition = self.transitions.iter().find(|transition| {
+            transition.from == state
+                && transition.event == event
+                && transition
+                    .guard
+                    .as_ref()
+                    .is_none_or(|(_, guard)| guard(context))
+        })?;
+        let external = transition.to != state;
+
+        if external {
+            run_hooks(&self.exit_hooks, state, context);
+        }
+        if let Some(action) = &transition.action {
+            action(context);
+        }
+        if external {
+            run_hooks(&self.entry_hooks, transition.to, context);
+        }
+
+        Some(transition.to)
+    }
+
+    // Get events, which have a transition from the state (guards aren't checked).
+    pub fn events(&self, state: S) -> Vec<&E> {
+        let mut events: Vec<&E> = Vec::new();
+
+        for transition in &self.transitions {
+            if transition.from == state && !events.contains(&&transition.event) {
+                events.push(&transition.event);
+            }
+        }
+
+        events
+    }
+
+    /*
+        Dump the transition table as a Graphviz DOT graph, for documentation:
+        edges are labeled with events and names of guards.
+    */
+    pub fn to_dot(&self, name: &str) -> String
+    where
+        S: fmt::Display,
+        E: fmt::Display,
+    {
+        let mut states = vec![self.initial];
+        for transition in &self.transitions {
+            for state in [transition.from, transition.to] {
+                if !states.contains(&state) {
+                    states.push(state);
+                }
+            }
+        }
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph {} {{", name).unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    start [shape=point];").unwrap();
+        for state in &states {
+            writeln!(dot, "    \"{}\" [shape=box];", state).unwrap();
+        }
+        writeln!(dot, "    start -> \"{}\";", self.initial).unwrap();
+
+        for transition in &self.transitions {
+            let label = match &transition.guard {
+                Some((guard, _)) => format!("{} [{}]", transition.event, guard),
+                None => transition.event.to_string(),
+            };
+            writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                transition.from, transition.to, label
+            )
+            .unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// Run hooks of the state in order they are added.
+fn run_hooks<S: PartialEq, C>(hooks: &[(S, Hook<C>)], state: S, context: &mut C) {
+    for (hooked, hook) in hooks {
+        if *hooked == state {
+            hook(context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Order processing: a workflow, which has nothing to do with posts.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Order {
+        New,
+        Paid,
+        Shipped,
+        Cancelled,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Event {
+        Pay,
+        Ship,
+        Cancel,
+    }
+
+    impl fmt::Display for Order {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl fmt::Display for Event {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    #[derive(Default)]
+    struct Cart {
+        total: u32,
+        paid: u32,
+        log: Vec<String>,
+    }
+
+    fn orders() -> StateMachine<Order, Event, Cart> {
+        StateMachine::new(Order::New)
+            .with(
+                Transition::new(Order::New, Event::Pay, Order::Paid)
+                    .guard("paid in full", |cart: &Cart| cart.paid >= cart.total)
+                    .action(|cart| cart.log.push(String::from("receipt"))),
+            )
+            .with(Transition::new(Order::New, Event::Pay, Order::New))
+            .with(Transition::new(Order::Paid, Event::Ship, Order::Shipped))
+            .with(Transition::new(Order::New, Event::Cancel, Order::Cancelled))
+            .with(Transition::new(
+                Order::Paid,
+                Event::Cancel,
+                Order::Cancelled,
+            ))
+            .on_exit(Order::New, |cart| cart.log.push(String::from("exit New")))
+            .on_entry(Order::Cancelled, |cart| cart.paid = 0)
+    }
+
+    #[test]
+    fn guards_and_hooks() {
+        let machine = orders();
+        let mut cart = Cart {
+            total: 10,
+            paid: 5,
+            ..Cart::default()
+        };
+
+        // Guard refuses the first row, the second one keeps the order new without hooks
+        let state = machine.fire(Order::New, Event::Pay, &mut cart).unwrap();
+        assert_eq!(Order::New, state);
+        assert!(cart.log.is_empty());
+        assert_eq!(None, machine.fire(state, Event::Ship, &mut cart));
+
+        cart.paid = 10;
+        let state = machine.fire(state, Event::Pay, &mut cart).unwrap();
+        assert_eq!(Order::Paid, state);
+        assert_eq!(["exit New", "receipt"], cart.log.as_slice());
+
+        let state = machine.fire(state, Event::Cancel, &mut cart).unwrap();
+        assert_eq!(Order::Cancelled, state);
+        assert_eq!(0, cart.paid);
+        assert!(machine.events(state).is_empty());
+        assert_eq!(
+            vec![&Event::Ship, &Event::Cancel],
+            machine.events(Order::Paid)
+        );
+    }
+
+    #[test]
+    fn dot_dump() {
+        let dot = orders().to_dot("Order");
+
+        assert!(dot.starts_with("digraph Order {\n"));
+        assert!(dot.contains("    start -> \"New\";\n"));
+        assert!(dot.contains("    \"New\" -> \"Paid\" [label=\"Pay [paid in full]\"];\n"));
+        assert!(dot.contains("    \"Paid\" -> \"Cancelled\" [label=\"Cancel\"];\n"));
+        assert_eq!(4, dot.matches("[shape=box]").count());
+    }
+}
@@ -15,6 +15,11 @@
     perhaps add more state objects.
 */
 
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use state_design_pattern::clock::{Clock, ManualClock};
+use state_design_pattern::typed::{self, Review};
 #[allow(dead_code)]
 #[allow(unused_variables)]
 use state_design_pattern::Post;
@@ -23,12 +28,58 @@ fn main() {
     /* Create a post */
     let mut post = Post::new();
 
-    post.add_text("I ate a salad for lunch today!");
+    post.add_text("I ate a salad for lunch today!").unwrap();
     assert_eq!("", post.content()); // No text, we are only added text
 
-    post.request_review();
+    post.request_review("author").unwrap();
     assert_eq!("", post.content()); // Not text, we are requesting a review
 
-    post.approve();
-    assert_eq!("I ate a salad for lunch today!", post.content()); // Text is approved
+    post.reject("alice").unwrap();
+    assert_eq!("", post.content()); // No text, review is rejected, post is a draft again
+
+    post.request_review("author").unwrap();
+    post.approve("alice").unwrap();
+    assert_eq!("", post.content()); // No text, one more approval is needed
+
+    post.approve("bob").unwrap();
+    assert_eq!("I ate a salad for lunch today!", post.content()); // Text is approved twice
+
+    post.set_strict(true);
+    assert!(post.reject("carol").is_err()); // Published post can't be rejected
+    assert_eq!(6, post.audit_log().len()); // Every attempt is in the log, refused one too
+
+    /* Scheduled post: it is published on time, and archived after the expiry */
+    let clock = Arc::new(ManualClock::new(SystemTime::now()));
+    let mut post = Post::with_clock(clock.clone());
+    post.add_text("Lunch menu of the week").unwrap();
+    post.schedule(clock.now() + Duration::from_secs(60))
+        .unwrap();
+    post.set_expiry(clock.now() + Duration::from_secs(120))
+        .unwrap();
+    post.request_review("author").unwrap();
+    post.approve("alice").unwrap();
+    post.approve("bob").unwrap();
+    assert_eq!("Scheduled", post.state()); // Approved, but it isn't time yet
+
+    clock.advance(Duration::from_secs(60));
+    post.tick(clock.now());
+    assert_eq!("Lunch menu of the week", post.content()); // Published on time
+
+    clock.advance(Duration::from_secs(60));
+    post.tick(clock.now());
+    assert_eq!("Archived", post.state()); // Expired post is archived
+
+    /* The same post with type states: every transition returns a post of the next type */
+    let mut post = typed::Post::new();
+    post.add_text("I ate a salad for lunch today!");
+
+    let post = post.request_review().reject(); // Rejected post is a draft again
+    let post = match post.request_review().approve("alice") {
+        Review::Pending(post) => post, // One more approval is needed
+        Review::Published(_) => unreachable!(),
+    };
+
+    if let Review::Published(post) = post.approve("bob") {
+        assert_eq!("I ate a salad for lunch today!", post.content()); // Only published post has content
+    }
 }
@@ -0,0 +1,108 @@
+// Revisions of the Post content: every edit is kept, and any two of them can be compared.
+
+use std::error::Error;
+use std::fmt;
+use std::time::SystemTime;
+
+// Define a single revision: the whole content after the edit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision {
+    pub number: usize,
+    pub timestamp: SystemTime,
+    pub content: String,
+}
+
+// Define errors of editing: Post isn't a draft, or revision doesn't exist.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditError {
+    NotDraft { state: &'static str },
+    UnknownRevision(usize),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::NotDraft { state } => {
+                write!(f, "Post can be edited only in state Draft, not {}", state)
+            }
+            EditError::UnknownRevision(number) => write!(f, "there is no revision {}", number),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+// Define a line of the diff between two revisions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+impl fmt::Display for DiffLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffLine::Same(line) => write!(f, " {}", line),
+            DiffLine::Added(line) => write!(f, "+{}", line),
+            DiffLine::Removed(line) => write!(f, "-{}", line),
+        }
+    }
+}
+
+/*
+    Compare texts line by line: lines of their longest common subsequence
+    stay the same, other lines are removed from the old text or added to it.
+*/
+pub fn diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // `common[i][j]` is the length of the common subsequence of `old[i..]` and `new[j..]`
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            // Removed lines go before added ones, like in unified diff
+            lines.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_diff() {
+        let lines: Vec<String> = diff("salad\nsoup\nbread", "salad\nbread\ntea")
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+
+        assert_eq!(vec![" salad", "-soup", " bread", "+tea"], lines);
+        assert!(diff("", "").is_empty());
+    }
+}
@@ -0,0 +1,519 @@
+// File-backed store of posts: one line per post, fields are `key=value` separated by tabs.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::audit::{Action, AuditEntry};
+use crate::clock::SystemClock;
+use crate::revision::Revision;
+use crate::{Post, PostState};
+
+/*
+  Keys of every record, in order they are written, older records miss
+  `audit`, `revisions`, `published` and the schedule, `content` is the latest revision
+*/
+const KEYS: [&str; 9] = [
+    "state",
+    "approvals",
+    "history",
+    "content",
+    "audit",
+    "revisions",
+    "published",
+    "publish_at",
+    "expires_at",
+];
+
+// Define errors of the store: file can't be used, or its contents are broken.
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Corrupted { line: usize, message: String },
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreError::Io(error) => write!(f, "{}", error),
+            StoreError::Corrupted { line, message } => {
+                write!(f, "corrupted record on line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(error: io::Error) -> Self {
+        StoreError::Io(error)
+    }
+}
+
+// Define a store, which keeps posts in a single file.
+pub struct Store {
+    path: PathBuf,
+}
+
+impl Store {
+    // Create a store for the file, the file is created on the first save.
+    pub fn new(path: impl Into<PathBuf>) -> Store {
+        Store { path: path.into() }
+    }
+
+    // Save all posts, the file is replaced at once, so it is never half written.
+    pub fn save(&self, posts: &[Post]) -> Result<(), StoreError> {
+        let contents: String = posts.iter().map(encode).collect();
+        let temp_path = self.path.with_extension(format!("tmp-{}", process::id()));
+
+        let written = (|| {
+            let mut file = File::create(&temp_path)?;
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&temp_path, &self.path)
+        })();
+
+        // Don't leave temporary file behind, when something failed.
+        if written.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        Ok(written?)
+    }
+
+    // Load all posts, missing file is an empty store.
+    pub fn load(&self) -> Result<Vec<Post>, StoreError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(index, line)| {
+                decode(line).map_err(|message| StoreError::Corrupted {
+                    line: index + 1,
+                    message,
+                })
+            })
+            .collect()
+    }
+}
+
+// Encode the post as a single record line.
+fn encode(post: &Post) -> String {
+    let list = |items: &[String]| -> String {
+        let items: Vec<String> = items.iter().map(|item| escape(item, true)).collect();
+        items.join(",")
+    };
+
+    let values = [
+        post.state().to_string(),
+        list(post.approvals()),
+        list(post.history()),
+        escape(post.text(), false),
+        list(&post.audit_log.iter().map(encode_entry).collect::<Vec<_>>()),
+        list(
+            &post
+                .revisions
+                .iter()
+                .map(encode_revision)
+                .collect::<Vec<_>>(),
+        ),
+        post.published
+            .map_or(String::new(), |number| number.to_string()),
+        post.publish_at
+            .map_or(String::new(), |time| encode_time(time).to_string()),
+        post.expires_at
+            .map_or(String::new(), |time| encode_time(time).to_string()),
+    ];
+
+    let fields: Vec<String> = KEYS
+        .iter()
+        .zip(values)
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    format!("{}\n", fields.join("\t"))
+}
+
+// Decode the record line, finding the state by its name.
+fn decode(line: &str) -> Result<Post, String> {
+    let mut values: [Option<&str>; 9] = [None; 9];
+
+    for field in line.split('\t') {
+        let (key, value) = field
+            .split_once('=')
+            .ok_or_else(|| format!("expected `key=value`, found `{}`", field))?;
+        let index = KEYS
+            .iter()
+            .position(|known| *known == key)
+            .ok_or_else(|| format!("unknown key `{}`", key))?;
+
+        if values[index].replace(value).is_some() {
+            return Err(format!("duplicate key `{}`", key));
+        }
+    }
+
+    let value =
+        |index: usize| values[index].ok_or_else(|| format!("missing key `{}`", KEYS[index]));
+    let name = unescape(value(0)?)?;
+    let approvals = split_list(value(1)?)?;
+    let history = split_list(value(2)?)?;
+    let content = unescape(value(3)?)?;
+    let audit_log = split_list(values[4].unwrap_or(""))?
+        .iter()
+        .map(|entry| decode_entry(entry))
+        .collect::<Result<Vec<_>, _>>()?;
+    let revisions = match values[5] {
+        Some(revisions) => split_list(revisions)?
+            .iter()
+            .enumerate()
+            .map(|(index, revision)| decode_revision(index + 1, revision))
+            .collect::<Result<Vec<_>, _>>()?,
+        // Older record has only the content, it becomes the first revision
+        None if content.is_empty() => Vec::new(),
+        None => vec![Revision {
+            number: 1,
+            timestamp: UNIX_EPOCH,
+            content: content.clone(),
+        }],
+    };
+
+    let state = PostState::from_name(&name).ok_or_else(|| format!("unknown state `{}`", name))?;
+
+    if let Some(unknown) = history
+        .iter()
+        .find(|name| PostState::from_name(name).is_none())
+    {
+        return Err(format!("unknown state `{}` in history", unknown));
+    }
+    if history.last() != Some(&name) {
+        return Err(String::from("history doesn't end with the current state"));
+    }
+    if revisions
+        .last()
+        .map_or("", |revision| revision.content.as_str())
+        != content
+    {
+        return Err(String::from("content doesn't match the last revision"));
+    }
+
+    let published = match values[6] {
+        Some("") => None,
+        Some(number) => match number.parse::<usize>() {
+            Ok(number) if (1..=revisions.len()).contains(&number) => Some(number),
+            _ => return Err(format!("unknown published revision `{}`", number)),
+        },
+        None if state == PostState::Published => Some(revisions.len()),
+        None => None,
+    };
+    let time = |index: usize| match values[index] {
+        None | Some("") => Ok(None),
+        Some(millis) => decode_time(millis)
+            .map(Some)
+            .ok_or_else(|| format!("invalid `{}` time `{}`", KEYS[index], millis)),
+    };
+    let publish_at = time(7)?;
+    let expires_at = time(8)?;
+
+    Ok(Post {
+        state,
+        revisions,
+        published,
+        approvals,
+        history,
+        audit_log,
+        strict: false,
+        publish_at,
+        expires_at,
+        clock: Arc::new(SystemClock),
+    })
+}
+
+// Time as milliseconds since the Unix epoch.
+fn encode_time(timestamp: SystemTime) -> u128 {
+    timestamp
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis())
+}
+
+fn decode_time(millis: &str) -> Option<SystemTime> {
+    Some(UNIX_EPOCH + Duration::from_millis(millis.parse().ok()?))
+}
+
+// Encode the revision as `milliseconds/content`.
+fn encode_revision(revision: &Revision) -> String {
+    format!("{}/{}", encode_time(revision.timestamp), revision.content)
+}
+
+// Decode the revision, numbers of revisions follow their order.
+fn decode_revision(number: usize, text: &str) -> Result<Revision, String> {
+    let invalid = || format!("invalid revision {}", number);
+    let (millis, content) = text.split_once('/').ok_or_else(invalid)?;
+
+    Ok(Revision {
+        number,
+        timestamp: decode_time(millis).ok_or_else(invalid)?,
+        content: content.to_string(),
+    })
+}
+
+// Encode the audit entry as `milliseconds/action/from/to/accepted/actor`.
+fn encode_entry(entry: &AuditEntry) -> String {
+    let millis = encode_time(entry.timestamp);
+    let accepted = if entry.accepted {
+        "accepted"
+    } else {
+        "refused"
+    };
+
+    format!(
+        "{}/{}/{}/{}/{}/{}",
+        millis,
+        entry.action.name(),
+        entry.from,
+        entry.to,
+        accepted,
+        entry.actor
+    )
+}
+
+// Decode the audit entry, actor is the last, so it can have any characters.
+fn decode_entry(text: &str) -> Result<AuditEntry, String> {
+    let invalid = || format!("invalid audit entry `{}`", text);
+    let state = |name: &str| PostState::from_name(name).map(|state| state.name());
+
+    let parts: Vec<&str> = text.splitn(6, '/').collect();
+    let [millis, action, from, to, accepted, actor] = parts[..] else {
+        return Err(invalid());
+    };
+
+    Ok(AuditEntry {
+        timestamp: decode_time(millis).ok_or_else(invalid)?,
+        actor: actor.to_string(),
+        action: Action::from_name(action).ok_or_else(invalid)?,
+        from: state(from).ok_or_else(invalid)?,
+        to: state(to).ok_or_else(invalid)?,
+        accepted: match accepted {
+            "accepted" => true,
+            "refused" => false,
+            _ => return Err(invalid()),
+        },
+    })
+}
+
+// Escape characters, which separate records and fields (and items of lists).
+fn escape(text: &str, item: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            ',' if item => escaped.push_str("\\,"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// Unescape a single value, separators are plain characters in it.
+fn unescape(text: &str) -> Result<String, String> {
+    let mut items = split_escaped(text, None)?;
+    Ok(items.pop().unwrap_or_default())
+}
+
+// Split comma separated list, empty text is an empty list.
+fn split_list(text: &str) -> Result<Vec<String>, String> {
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+    split_escaped(text, Some(','))
+}
+
+// Unescape the text, splitting it on separator, which isn't escaped.
+fn split_escaped(text: &str, separator: Option<char>) -> Result<Vec<String>, String> {
+    let mut items = vec![String::new()];
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        let current = items.last_mut().unwrap();
+
+        match c {
+            '\\' => match chars.next() {
+                Some('\\') => current.push('\\'),
+                Some('t') => current.push('\t'),
+                Some('n') => current.push('\n'),
+                Some('r') => current.push('\r'),
+                Some(',') => current.push(','),
+                _ => return Err(String::from("invalid escape sequence")),
+            },
+            c if Some(c) == separator => items.push(String::new()),
+            c => current.push(c),
+        }
+    }
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_store(name: &str) -> (Store, PathBuf) {
+        let path = env::temp_dir().join(format!("posts-{}-{}.txt", name, process::id()));
+        (Store::new(&path), path)
+    }
+
+    #[test]
+    fn save_and_load() {
+        let (store, path) = temp_store("save");
+
+        let mut draft = Post::new();
+        draft.add_text("tabs\tand\nlines, \\ too").unwrap();
+
+        let mut published = Post::new();
+        published
+            .add_text("I ate a salad for lunch today!")
+            .unwrap();
+        published.request_review("author").unwrap();
+        published.reject("bob").unwrap();
+        published.request_review("author").unwrap();
+        published.approve("alice, the editor").unwrap();
+        published.approve("bob").unwrap();
+
+        let mut scheduled = Post::new();
+        let publish_at = UNIX_EPOCH + Duration::from_secs(4_000_000_000);
+        scheduled.schedule(publish_at).unwrap();
+        scheduled
+            .set_expiry(publish_at + Duration::from_secs(60))
+            .unwrap();
+        scheduled.request_review("author").unwrap();
+        scheduled.approve("alice").unwrap();
+        scheduled.approve("bob").unwrap();
+
+        store.save(&[draft, published, scheduled]).unwrap();
+        let posts = store.load().unwrap();
+
+        assert_eq!(3, posts.len());
+        assert_eq!("Draft", posts[0].state());
+        assert_eq!("tabs\tand\nlines, \\ too", posts[0].text());
+        assert_eq!(1, posts[1].revisions().len());
+        assert_eq!("I ate a salad for lunch today!", posts[1].content());
+        assert_eq!(["alice, the editor", "bob"], posts[1].approvals());
+        assert_eq!(
+            [
+                "Draft",
+                "PendingReview",
+                "Draft",
+                "PendingReview",
+                "Published"
+            ],
+            posts[1].history()
+        );
+        assert_eq!(
+            vec![
+                ("author", "request_review", "Draft", "PendingReview", true),
+                ("bob", "reject", "PendingReview", "Draft", true),
+                ("author", "request_review", "Draft", "PendingReview", true),
+                (
+                    "alice, the editor",
+                    "approve",
+                    "PendingReview",
+                    "PendingReview",
+                    true
+                ),
+                ("bob", "approve", "PendingReview", "Published", true),
+            ],
+            posts[1]
+                .audit_log()
+                .iter()
+                .map(|entry| (
+                    entry.actor.as_str(),
+                    entry.action.name(),
+                    entry.from,
+                    entry.to,
+                    entry.accepted
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        // Schedule is kept, so the loaded post moves on time
+        let mut scheduled = posts.into_iter().nth(2).unwrap();
+        assert_eq!("Scheduled", scheduled.state());
+        assert!(scheduled.tick(publish_at + Duration::from_secs(60)));
+        assert_eq!("Archived", scheduled.state());
+
+        fs::remove_file(&path).unwrap();
+        assert!(store.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn corrupted_records() {
+        let (store, path) = temp_store("corrupted");
+        let record = |state: &str, history: &str| {
+            format!(
+                "state={}\tapprovals=\thistory={}\tcontent=text\n",
+                state, history
+            )
+        };
+        let error = |contents: String| {
+            fs::write(&path, contents).unwrap();
+            store.load().err().unwrap().to_string()
+        };
+
+        assert_eq!(
+            "corrupted record on line 2: unknown state `Deleted`",
+            error(record("Draft", "Draft") + &record("Deleted", "Draft,Deleted"))
+        );
+        assert_eq!(
+            "corrupted record on line 1: unknown state `Lost` in history",
+            error(record("Draft", "Lost,Draft"))
+        );
+        assert_eq!(
+            "corrupted record on line 1: history doesn't end with the current state",
+            error(record("Published", "Draft"))
+        );
+        assert_eq!(
+            "corrupted record on line 1: invalid audit entry `1/delete/Draft/Draft/refused/bob`",
+            error(
+                record("Draft", "Draft")
+                    .replace("\n", "\taudit=1/delete/Draft/Draft/refused/bob\n")
+            )
+        );
+        assert_eq!(
+            "corrupted record on line 1: missing key `content`",
+            error(String::from("state=Draft\tapprovals=\thistory=Draft"))
+        );
+        assert_eq!(
+            "corrupted record on line 1: content doesn't match the last revision",
+            error(record("Draft", "Draft").replace("\n", "\trevisions=1/other\n"))
+        );
+        assert_eq!(
+            "corrupted record on line 1: unknown published revision `2`",
+            error(record("Draft", "Draft").replace("\n", "\tpublished=2\n"))
+        );
+        assert_eq!(
+            "corrupted record on line 1: invalid `expires_at` time `soon`",
+            error(record("Draft", "Draft").replace("\n", "\texpires_at=soon\n"))
+        );
+
+        // Older record without revisions is published with its content
+        fs::write(&path, record("Published", "Draft,Published")).unwrap();
+        assert_eq!("text", store.load().unwrap()[0].content());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
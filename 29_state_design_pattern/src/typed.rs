@@ -0,0 +1,135 @@
+//! Type-state variant of the blog post: every state is its own type, so invalid
+//! transitions are compile errors, not silent no-ops.
+//!
+//! Only published post has content:
+//!
+//! ```compile_fail
+//! use state_design_pattern::typed::Post;
+//!
+//! let mut post = Post::new();
+//! post.add_text("I ate a salad for lunch today!");
+//! post.content();
+//! ```
+//!
+//! Draft can't be approved without a review:
+//!
+//! ```compile_fail
+//! use state_design_pattern::typed::Post;
+//!
+//! let post = Post::new();
+//! post.approve("alice");
+//! ```
+//!
+//! Post under review can't be edited:
+//!
+//! ```compile_fail
+//! use state_design_pattern::typed::Post;
+//!
+//! let mut post = Post::new().request_review();
+//! post.add_text("more text");
+//! ```
+//!
+//! Published post can't be rejected:
+//!
+//! ```compile_fail
+//! use state_design_pattern::typed::{Post, Review};
+//!
+//! let mut post = Post::new().request_review();
+//! post = match post.approve("alice") {
+//!     Review::Pending(post) => post,
+//!     Review::Published(_) => unreachable!(),
+//! };
+//! if let Review::Published(post) = post.approve("bob") {
+//!     post.reject();
+//! }
+//! ```
+
+use crate::REQUIRED_APPROVALS;
+
+// Define a published Post, the only state, which has content.
+pub struct Post {
+    content: String,
+    approvals: Vec<String>,
+}
+
+// Define a Post, which is still being written.
+pub struct DraftPost {
+    content: String,
+}
+
+// Define a Post, which waits for approvals.
+pub struct PendingReviewPost {
+    content: String,
+    approvals: Vec<String>,
+}
+
+// Define result of approval: Post waits for more approvals, or it is published.
+pub enum Review {
+    Pending(PendingReviewPost),
+    Published(Post),
+}
+
+impl Post {
+    // Create a new Post, it starts as a draft with empty content.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> DraftPost {
+        DraftPost {
+            content: String::new(),
+        }
+    }
+
+    // Get the content of the Post.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    // Get the approvers of the Post (in order of approval).
+    pub fn approvals(&self) -> &[String] {
+        &self.approvals
+    }
+}
+
+impl DraftPost {
+    // Add text to the content of the Post.
+    pub fn add_text(&mut self, text: &str) {
+        self.content.push_str(text);
+    }
+
+    // Transition the Post to the PendingReview state.
+    pub fn request_review(self) -> PendingReviewPost {
+        PendingReviewPost {
+            content: self.content,
+            approvals: Vec::new(),
+        }
+    }
+}
+
+impl PendingReviewPost {
+    // Transition the Post back to the Draft state, approvals are dropped.
+    pub fn reject(self) -> DraftPost {
+        DraftPost {
+            content: self.content,
+        }
+    }
+
+    // Approve the Post on behalf of the approver, it is published after enough distinct approvals.
+    pub fn approve(mut self, approver: &str) -> Review {
+        // The same approver can't approve twice.
+        if !self.approvals.iter().any(|approval| approval == approver) {
+            self.approvals.push(approver.to_string());
+        }
+
+        if self.approvals.len() < REQUIRED_APPROVALS {
+            return Review::Pending(self);
+        }
+        Review::Published(Post {
+            content: self.content,
+            approvals: self.approvals,
+        })
+    }
+
+    // Get the approvers of the Post (in order of approval).
+    pub fn approvals(&self) -> &[String] {
+        &self.approvals
+    }
+}